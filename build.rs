@@ -0,0 +1,43 @@
+use std::{env, fmt::Write, fs, path::Path};
+
+#[allow(dead_code)]
+#[path = "src/codec/dqt_values.rs"]
+mod dqt_values;
+
+use dqt_values::{fill_dqt_row_all_residuals, DQT_ROW_LEN};
+
+// scale factor sizes that get precomputed 16-bit tables, 4 bits is the encoder default
+const STATIC_SCALE_FACTOR_BITS: [usize; 3] = [3, 4, 5];
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/codec/dqt_values.rs");
+
+    if env::var_os("CARGO_FEATURE_STATIC_TABLES").is_none() {
+        return;
+    }
+
+    let mut tables = String::new();
+    for scale_factor_bits in STATIC_SCALE_FACTOR_BITS {
+        let scale_factors = 1 << scale_factor_bits;
+        writeln!(
+            tables,
+            "/// Dequantization tables for {scale_factor_bits} bit scale factors, generated at build time"
+        )
+        .unwrap();
+        writeln!(
+            tables,
+            "pub static STATIC_TABLES_{scale_factor_bits}: DequantTables<{scale_factors}> = DequantTables::from_values(["
+        )
+        .unwrap();
+        for index in 0..scale_factors {
+            let mut row = [0; DQT_ROW_LEN];
+            fill_dqt_row_all_residuals(&mut row, scale_factor_bits, 16, index);
+            writeln!(tables, "    {row:?},").unwrap();
+        }
+        writeln!(tables, "]);").unwrap();
+    }
+
+    let out_dir = env::var_os("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("dqt_tables.rs"), tables).unwrap();
+}
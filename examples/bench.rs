@@ -8,7 +8,7 @@ use std::io::Write;
 use std::{fs, io::BufWriter, path::Path, time::Instant};
 
 use sea_codec::{encoder::EncoderSettings, sea_decode, sea_encode};
-use wav::{read_wav, write_wav, Wave};
+use wav::{read_wav, write_wav};
 
 const SAMPLE_NAME: &str = "piano_long";
 const BASE_PATH: &str = "E:/audio_samples/mixed";
@@ -16,12 +16,13 @@ const RESIDUAL_SIZE: f32 = 5.0;
 
 fn encode_decode() {
     let input_wave_path = &format!("{}/{}.wav", BASE_PATH, SAMPLE_NAME);
-    let input_wave_full = read_wav(&Path::new(input_wave_path)).unwrap();
-    let input_wave = Wave {
-        samples: input_wave_full.samples, // [256..256 + 128].to_vec(),
-        sample_rate: input_wave_full.sample_rate,
-        channels: input_wave_full.channels,
-    };
+    let input_wave = read_wav(Path::new(input_wave_path)).unwrap();
+    // measured at 16 bits
+    let input_samples: Vec<i16> = input_wave
+        .samples
+        .iter()
+        .map(|&s| (s >> (input_wave.sample_depth - 16)) as i16)
+        .collect(); // [256..256 + 128].to_vec();
 
     let settings = EncoderSettings {
         residual_bits: RESIDUAL_SIZE,
@@ -33,17 +34,18 @@ fn encode_decode() {
 
     let now = Instant::now();
     let sea_file = sea_encode(
-        &input_wave.samples,
+        &input_samples,
         input_wave.sample_rate,
         input_wave.channels,
         settings.clone(),
-    );
-    let bits_per_sample = (sea_file.len() as f32 * 8.0) / input_wave.samples.len() as f32;
+    )
+    .unwrap();
+    let bits_per_sample = (sea_file.len() as f32 * 8.0) / input_samples.len() as f32;
     println!("Encoding took {}ms", now.elapsed().as_millis());
 
     println!(
         "Compression ratio {:.2}",
-        (input_wave.samples.len() as f64 * 2.0) / sea_file.len() as f64
+        (input_samples.len() as f64 * 2.0) / sea_file.len() as f64
     );
 
     let sea_output_filename = format!(
@@ -56,9 +58,9 @@ fn encode_decode() {
     BufWriter::new(file).write_all(&sea_file).unwrap();
 
     let now = Instant::now();
-    let decoded = sea_decode(&sea_file);
+    let decoded = sea_decode(&sea_file).unwrap();
     println!("Decoding took {}ms", now.elapsed().as_millis());
-    assert_eq!(input_wave.samples.len(), decoded.samples.len());
+    assert_eq!(input_samples.len(), decoded.samples.len());
 
     let vbr_string = if settings.vbr { "_vbr" } else { "" };
 
@@ -68,19 +70,23 @@ fn encode_decode() {
     );
 
     write_wav(
-        &decoded.samples,
+        &decoded
+            .samples
+            .iter()
+            .map(|&s| s as i32)
+            .collect::<Vec<_>>(),
         decoded.channels as u16,
         decoded.sample_rate as u32,
+        16,
         wav_output_filename.as_str(),
     )
     .unwrap();
 
-    let sample_difference = input_wave
-        .samples
+    let sample_difference = input_samples
         .iter()
         .zip(decoded.samples.iter())
-        .map(|(a, b)| a - b)
-        .collect::<Vec<i16>>();
+        .map(|(&a, &b)| (a as i32 - b as i32).clamp(i16::MIN as i32, i16::MAX as i32))
+        .collect::<Vec<i32>>();
 
     let diff_wav_output_filename = format!(
         "{}/{}_after_sea_diff_{}{}.wav",
@@ -91,11 +97,12 @@ fn encode_decode() {
         &sample_difference,
         decoded.channels as u16,
         decoded.sample_rate as u32,
+        16,
         diff_wav_output_filename.as_str(),
     )
     .unwrap();
 
-    let quality = helpers::get_audio_quality(&input_wave.samples, &decoded.samples);
+    let quality = helpers::get_audio_quality(&input_samples, &decoded.samples);
     println!("Bits per sample: {:.2}", bits_per_sample);
     println!(
         "RMS: {:.4}% PSNR {:.2} dB",
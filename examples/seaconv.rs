@@ -1,7 +1,8 @@
 use clap::{Arg, ArgAction, ArgMatches, Command};
 use sea_codec::{
-    decoder::SeaDecoder,
-    encoder::{EncoderSettings, SeaEncoder},
+    encoder::{EncoderSettings, QualityTarget, SeaEncoder},
+    metadata::SeaMetadata,
+    sea_decode_i32, SeaError,
 };
 use std::{
     io::{Read, Write},
@@ -22,11 +23,6 @@ fn get_encoder_settings(matches: &ArgMatches) -> EncoderSettings {
             std::process::exit(1);
         });
 
-    if frames_per_chunk < 200 || frames_per_chunk > 32000 {
-        eprintln!("Error: Chunk size must be between 200 and 32000");
-        std::process::exit(1);
-    }
-
     let scale_factor_bits = matches
         .get_one::<String>("scalefactor-bits")
         .unwrap()
@@ -36,11 +32,6 @@ fn get_encoder_settings(matches: &ArgMatches) -> EncoderSettings {
             std::process::exit(1);
         });
 
-    if scale_factor_bits < 3 || scale_factor_bits > 5 {
-        eprintln!("Error: Scale factor bits must be between 3 and 5");
-        std::process::exit(1);
-    }
-
     let scale_factor_frames = matches
         .get_one::<String>("scalefactor-distance")
         .unwrap()
@@ -50,11 +41,6 @@ fn get_encoder_settings(matches: &ArgMatches) -> EncoderSettings {
             std::process::exit(1);
         });
 
-    if scale_factor_frames < 1 || frames_per_chunk % scale_factor_frames as u16 != 0 {
-        eprintln!("Error: Scale factor frames must be a divisor of chunk size");
-        std::process::exit(1);
-    }
-
     let residual_bits = matches
         .get_one::<String>("bitrate")
         .unwrap()
@@ -64,43 +50,124 @@ fn get_encoder_settings(matches: &ArgMatches) -> EncoderSettings {
             std::process::exit(1);
         });
 
-    if residual_bits < 1.0 || residual_bits > 8.0 {
-        eprintln!("Error: Bitrate must be between 1.0 and 8.0");
-        std::process::exit(1);
-    }
+    let lms_order = matches
+        .get_one::<String>("lms-order")
+        .unwrap()
+        .parse::<u8>()
+        .unwrap_or_else(|_| {
+            eprintln!("Error: Failed to parse LMS order");
+            std::process::exit(1);
+        });
 
     let vbr = matches.get_flag("vbr");
-    let vbr_residual_beam_width = match matches.get_one::<String>("vbr-effort").map(String::as_str)
-    {
+    let vbr_residual_beam_width = match matches.get_one::<String>("effort").map(String::as_str) {
         None | Some("fast") => 0,
         Some("low") => 1,
         Some("mid") => 2,
         Some("high") => 3,
         Some("ultra") => 4,
-        Some(_) => unreachable!("clap validates --vbr-effort"),
+        Some(_) => unreachable!("clap validates --effort"),
     };
 
-    if vbr {
-        if !(1.5..=8.0).contains(&residual_bits) {
-            eprintln!("Error: With VBR, bitrate must be between 1.5 and 8.0");
-            std::process::exit(1);
-        }
-    } else {
-        if residual_bits.fract() != 0.0 || !(1..=8).contains(&(residual_bits as i32)) {
-            eprintln!("Error: Without VBR, bitrate must be an integer between 1 and 8");
+    let parse_db = |db: &String| {
+        db.parse::<f32>().unwrap_or_else(|_| {
+            eprintln!("Error: Failed to parse quality target");
             std::process::exit(1);
-        }
-    }
+        })
+    };
+    let quality_target = match (
+        matches.get_one::<String>("snr"),
+        matches.get_one::<String>("psnr"),
+    ) {
+        (Some(db), _) => Some(QualityTarget::Snr(parse_db(db))),
+        (_, Some(db)) => Some(QualityTarget::Psnr(parse_db(db))),
+        _ => None,
+    };
 
-    EncoderSettings {
+    let settings = EncoderSettings {
         scale_factor_bits,
         scale_factor_frames,
         residual_bits,
         vbr,
         vbr_residual_beam_width,
         frames_per_chunk,
+        checksum: matches.get_flag("checksum"),
+        sync: matches.get_flag("sync"),
+        quality_target,
+        mid_side: matches.get_flag("mid-side"),
+        lms_order,
         ..Default::default()
+    };
+
+    if let Err(SeaError::InvalidSettings { field, reason }) = settings.validate() {
+        let option = match field {
+            "frames_per_chunk" => "--chunk-size",
+            "scale_factor_bits" => "--scalefactor-bits",
+            "scale_factor_frames" => "--scalefactor-distance",
+            "residual_bits" => "--bitrate",
+            "lms_order" => "--lms-order",
+            "quality_target" if matches.contains_id("snr") => "--snr",
+            "quality_target" => "--psnr",
+            field => field,
+        };
+        eprintln!("Error: {} {}", option, reason);
+        std::process::exit(1);
+    }
+
+    settings
+}
+
+/// Bits per sample requested with `--depth`, `None` to keep the depth of the input
+fn get_sample_depth(matches: &ArgMatches) -> Option<u8> {
+    let sample_depth = matches.get_one::<String>("depth")?.parse::<u8>().ok();
+    if !matches!(sample_depth, Some(16 | 20 | 24)) {
+        eprintln!("Error: Sample depth must be 16, 20 or 24");
+        std::process::exit(1);
+    }
+    sample_depth
+}
+
+/// Converts samples between sample depths, dropping the low bits when reducing
+fn convert_depth(samples: &[i32], from: u8, to: u8) -> Vec<i32> {
+    samples
+        .iter()
+        .map(|&s| {
+            if to >= from {
+                s << (to - from)
+            } else {
+                s >> (from - to)
+            }
+        })
+        .collect()
+}
+
+#[cfg(feature = "resample")]
+fn resample(samples: &[i32], sample_depth: u8, from: u32, to: u32, channels: u32) -> Vec<i32> {
+    // the resampler works on 16-bit samples
+    let samples: Vec<i16> = convert_depth(samples, sample_depth, 16)
+        .into_iter()
+        .map(|s| s as i16)
+        .collect();
+    sea_codec::resample::resample(&samples, from, to, channels)
+        .into_iter()
+        .map(i32::from)
+        .collect()
+}
+
+fn get_metadata(matches: &ArgMatches) -> SeaMetadata {
+    let mut metadata = SeaMetadata::new();
+
+    for pair in matches.get_many::<String>("metadata").into_iter().flatten() {
+        let inserted = pair
+            .split_once('=')
+            .is_some_and(|(key, value)| metadata.insert(key, value).is_ok());
+        if !inserted {
+            eprintln!("Error: Metadata must be in key=value format");
+            std::process::exit(1);
+        }
     }
+
+    metadata
 }
 
 fn main() {
@@ -132,6 +199,23 @@ fn main() {
                 .help("Sets the bitrate for the conversion")
                 .default_value("3"),
         )
+        .arg(
+            Arg::new("kbps")
+                .long("kbps")
+                .help("Targets a bitrate in kbit/s, derives --bitrate and --scalefactor-distance and enables VBR"),
+        )
+        .arg(
+            Arg::new("snr")
+                .long("snr")
+                .conflicts_with_all(["kbps", "psnr"])
+                .help("Encodes every scale factor period with at least this signal to noise ratio in dB, chunks vary in size"),
+        )
+        .arg(
+            Arg::new("psnr")
+                .long("psnr")
+                .conflicts_with("kbps")
+                .help("Like --snr, relative to a full scale signal, so quiet passages use fewer bits"),
+        )
         .arg(
             Arg::new("scalefactor-bits")
                 .long("scalefactor-bits")
@@ -154,11 +238,41 @@ fn main() {
                 .help("Enables Variable Bit Rate (VBR)"),
         )
         .arg(
-            Arg::new("vbr-effort")
-                .long("vbr-effort")
+            Arg::new("effort")
+                .long("effort")
+                .visible_alias("vbr-effort")
                 .value_parser(["fast", "low", "mid", "high", "ultra"])
                 .default_value("fast")
-                .help("VBR effort: fast=scalar; low=1, mid=2, high=3, ultra=4 beam paths"),
+                .help("Encoder effort: fast=greedy; low=1, mid=2, high=3, ultra=4 beam paths"),
+        )
+        .arg(
+            Arg::new("checksum")
+                .long("checksum")
+                .action(ArgAction::SetTrue)
+                .help("Stores a CRC-32 checksum in every chunk"),
+        )
+        .arg(
+            Arg::new("sync")
+                .long("sync")
+                .action(ArgAction::SetTrue)
+                .help("Adds a sync header to every chunk for broadcast streams"),
+        )
+        .arg(
+            Arg::new("mid-side")
+                .long("mid-side")
+                .action(ArgAction::SetTrue)
+                .help("Encodes stereo as mid and side in chunks where that is cheaper"),
+        )
+        .arg(
+            Arg::new("lms-order")
+                .long("lms-order")
+                .help("Sets the LMS predictor order: 2, 4, 8 or 16")
+                .default_value("4"),
+        )
+        .arg(
+            Arg::new("depth")
+                .long("depth")
+                .help("Sets the bits per sample stored in the .sea file: 16, 20 or 24 [default: depth of the input]"),
         )
         .arg(
             Arg::new("resample")
@@ -166,9 +280,18 @@ fn main() {
                 .short('r')
                 .help("Sets the target sample rate for resampling"),
         )
+        .arg(
+            Arg::new("metadata")
+                .long("metadata")
+                .short('m')
+                .action(ArgAction::Append)
+                .help("Adds a key=value metadata entry to the encoded file"),
+        )
         .get_matches();
 
-    let settings = get_encoder_settings(&matches);
+    let mut settings = get_encoder_settings(&matches);
+    let sample_depth = get_sample_depth(&matches);
+    let metadata = get_metadata(&matches);
 
     let input = matches.get_one::<String>("input").unwrap();
     let output = matches.get_one::<String>("output").unwrap();
@@ -178,7 +301,7 @@ fn main() {
 
     match (input_ext, output_ext) {
         (Some("wav"), Some("sea")) => {
-            let input_wave = read_wav(&Path::new(input)).unwrap_or_else(|_| {
+            let input_wave = read_wav(Path::new(input)).unwrap_or_else(|_| {
                 eprintln!("Error: Failed to decode .wav file");
                 std::process::exit(1);
             });
@@ -192,6 +315,8 @@ fn main() {
             let mut samples = input_wave.samples;
             #[allow(unused_mut)]
             let mut sample_rate = input_wave.sample_rate;
+            #[allow(unused_mut)]
+            let mut input_depth = input_wave.sample_depth;
 
             if let Some(target_rate_str) = matches.get_one::<String>("resample") {
                 let target_rate = target_rate_str.parse::<u32>().unwrap_or_else(|_| {
@@ -204,13 +329,15 @@ fn main() {
 
                 #[cfg(feature = "resample")]
                 {
-                    samples = sea_codec::resample::resample(
+                    samples = resample(
                         &samples,
+                        input_depth,
                         sample_rate,
                         target_rate,
-                        input_wave.channels as u32,
+                        input_wave.channels,
                     );
                     sample_rate = target_rate;
+                    input_depth = 16;
                 }
                 #[cfg(not(feature = "resample"))]
                 {
@@ -219,21 +346,46 @@ fn main() {
                 }
             }
 
+            settings.sample_depth = sample_depth.unwrap_or(input_wave.sample_depth);
+            if let Some(kbps) = matches.get_one::<String>("kbps") {
+                let kbps = kbps.parse::<f32>().unwrap_or_else(|_| {
+                    eprintln!("Error: Failed to parse target bitrate");
+                    std::process::exit(1);
+                });
+                settings = settings
+                    .with_target_bitrate(
+                        (kbps * 1000.0) as u32,
+                        input_wave.channels as u8,
+                        sample_rate,
+                    )
+                    .unwrap_or_else(|err| {
+                        match err {
+                            SeaError::InvalidSettings { reason, .. } => {
+                                eprintln!("Error: --kbps {}", reason)
+                            }
+                            err => eprintln!("Error: {}", err),
+                        }
+                        std::process::exit(1);
+                    });
+            }
+            let samples = convert_depth(&samples, input_depth, settings.sample_depth);
+
             let mut sea_encoder = SeaEncoder::from_slice(
                 input_wave.channels as u8,
                 sample_rate,
-                Some(samples.len() as u32 / input_wave.channels as u32),
+                Some(samples.len() as u64 / input_wave.channels as u64),
                 settings,
                 &samples,
             )
-            .unwrap_or_else(|_| {
-                eprintln!("Error: Failed to create encoder");
+            .and_then(|encoder| encoder.with_metadata(metadata))
+            .unwrap_or_else(|err| {
+                eprintln!("Error: Failed to create encoder: {}", err);
                 std::process::exit(1);
             });
 
             let mut buf = Vec::new();
-            while sea_encoder.encode_frame(&mut buf).unwrap_or_else(|_| {
-                eprintln!("Error: Failed to encode frame");
+            while sea_encoder.encode_frame(&mut buf).unwrap_or_else(|err| {
+                eprintln!("Error: Failed to encode frame: {}", err);
                 std::process::exit(1);
             }) {
                 output_file.write_all(&buf).unwrap_or_else(|_| {
@@ -243,8 +395,8 @@ fn main() {
                 buf.clear();
             }
 
-            sea_encoder.finalize().unwrap_or_else(|_| {
-                eprintln!("Error: Failed to finalize encoder");
+            sea_encoder.finalize().unwrap_or_else(|err| {
+                eprintln!("Error: Failed to finalize encoder: {}", err);
                 std::process::exit(1);
             });
         }
@@ -257,22 +409,17 @@ fn main() {
             let mut content = Vec::new();
             input_file.read_to_end(&mut content).unwrap();
 
-            let mut sea_decoded = Vec::<i16>::with_capacity(64 * 1024 * 1024);
-            let mut sea_decoder = SeaDecoder::from_slice(&content).unwrap();
-
-            while sea_decoder
-                .decode_frame(&mut sea_decoded)
-                .unwrap_or_else(|_| {
-                    eprintln!("Error: Failed to decode frame");
-                    std::process::exit(1);
-                })
-            {}
+            let info = sea_decode_i32(&content).unwrap_or_else(|err| {
+                eprintln!("Error: Failed to decode file: {}", err);
+                std::process::exit(1);
+            });
 
-            let info = sea_decoder.get_header();
             #[allow(unused_mut)]
-            let mut samples = sea_decoded;
+            let mut samples = info.samples;
             #[allow(unused_mut)]
             let mut sample_rate = info.sample_rate;
+            #[allow(unused_mut)]
+            let mut output_depth = info.sample_depth;
 
             if let Some(target_rate_str) = matches.get_one::<String>("resample") {
                 let target_rate = target_rate_str.parse::<u32>().unwrap_or_else(|_| {
@@ -285,13 +432,15 @@ fn main() {
 
                 #[cfg(feature = "resample")]
                 {
-                    samples = sea_codec::resample::resample(
+                    samples = resample(
                         &samples,
+                        output_depth,
                         sample_rate,
                         target_rate,
-                        info.channels as u32,
+                        info.channels,
                     );
                     sample_rate = target_rate;
+                    output_depth = 16;
                 }
                 #[cfg(not(feature = "resample"))]
                 {
@@ -304,6 +453,7 @@ fn main() {
                 samples.as_slice(),
                 info.channels as u16,
                 sample_rate,
+                output_depth,
                 output,
             )
             .unwrap_or_else(|_| {
@@ -15,7 +15,8 @@ typedef struct {
     float residual_bits;
     uint16_t frames_per_chunk;
     bool vbr;
-    // VBR encoder effort: 0 = fast, 1 = low, 2 = mid, 3 = high, 4 = ultra.
+    // Encoder effort for CBR and VBR: 0 = fast, 1 = low, 2 = mid, 3 = high, 4 = ultra.
+    // Efforts above fast need scale_factor_frames of at most 20.
     uint8_t vbr_effort;
 } CSeaEncoderSettings;
 
@@ -37,6 +38,7 @@ int sea_encode(
 );
 
 // Decode
+// Returns 0 on success, non-zero on error (e.g. malformed input).
 // output_samples is allocated by the function and must be freed by sea_free_samples.
 int sea_decode(
     const uint8_t* encoded_data,
@@ -12,7 +12,8 @@ pub struct CSeaEncoderSettings {
     pub residual_bits: c_float,
     pub frames_per_chunk: u16,
     pub vbr: bool,
-    /// VBR encoder effort: 0 = fast, 1 = low, 2 = mid, 3 = high, 4 = ultra.
+    /// Encoder effort for CBR and VBR: 0 = fast, 1 = low, 2 = mid, 3 = high, 4 = ultra.
+    /// Efforts above fast need `scale_factor_frames` of at most 20.
     pub vbr_effort: u8,
 }
 
@@ -25,6 +26,7 @@ impl From<&CSeaEncoderSettings> for EncoderSettings {
             frames_per_chunk: c_settings.frames_per_chunk,
             vbr: c_settings.vbr,
             vbr_residual_beam_width: c_settings.vbr_effort,
+            ..Default::default()
         }
     }
 }
@@ -69,7 +71,10 @@ pub unsafe extern "C" fn sea_encode(
         EncoderSettings::from(&*settings)
     };
 
-    let mut encoded = rust_sea_encode(input_slice, sample_rate, channels, encoder_settings);
+    let Ok(mut encoded) = rust_sea_encode(input_slice, sample_rate, channels, encoder_settings)
+    else {
+        return -1;
+    };
 
     encoded.shrink_to_fit();
     let ptr = encoded.as_mut_ptr();
@@ -103,7 +108,9 @@ pub unsafe extern "C" fn sea_decode(
 
     let encoded_slice = slice::from_raw_parts(encoded_data, encoded_length);
 
-    let decode_info = rust_sea_decode(encoded_slice);
+    let Ok(decode_info) = rust_sea_decode(encoded_slice) else {
+        return -1;
+    };
 
     let mut samples = decode_info.samples;
     samples.shrink_to_fit();
@@ -1,16 +1,16 @@
-use alloc::vec;
+#[cfg(feature = "alloc")]
 use alloc::vec::Vec;
 
-use crate::{
-    codec::{bits::BitUnpacker, lms::LMS_LEN},
-    encoder::EncoderSettings,
-};
+use crate::codec::bits::BitReader;
+#[cfg(feature = "alloc")]
+use crate::encoder::EncoderSettings;
 
+#[cfg(feature = "alloc")]
+use super::{bits::BitPacker, file::SeaFileHeader, lms::SeaLMS};
 use super::{
-    bits::BitPacker,
     common::{SeaError, SeaResidualSize},
-    file::SeaFileHeader,
-    lms::SeaLMS,
+    crc::Crc32,
+    format::SeaFormat,
 };
 
 #[derive(Debug, Clone, Copy)]
@@ -19,16 +19,32 @@ pub enum SeaChunkType {
     Vbr = 0x02,
 }
 
+/// Chunk flag of version 2+ files, the first two channels store mid and side instead of
+/// left and right
+pub const CHUNK_FLAG_MID_SIDE: u8 = 0x01;
+
+/// CRC of a serialized chunk without its CRC field
+fn calculate_crc(chunk: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(&chunk[..4]);
+    crc.update(&chunk[8..]);
+    crc.finish()
+}
+
+#[cfg(feature = "alloc")]
 #[derive(Debug)]
 pub struct SeaChunk {
     pub channels: usize,
     pub frames_per_chunk: usize,
 
     pub chunk_type: SeaChunkType,
+    pub version: u8,
+    pub has_crc: bool,
 
     pub scale_factor_bits: u8,
     pub scale_factor_frames: u8,
     pub residual_size: SeaResidualSize,
+    pub mid_side: bool,
 
     pub lms: Vec<SeaLMS>,
 
@@ -37,11 +53,13 @@ pub struct SeaChunk {
     pub residuals: Vec<u8>,
 }
 
+#[cfg(feature = "alloc")]
 impl SeaChunk {
     pub fn new(
         file_header: &SeaFileHeader,
         lms: &[SeaLMS],
         encoder_settings: &EncoderSettings,
+        residual_size: SeaResidualSize,
         scale_factors: Vec<u8>,
         vbr_residual_sizes: Vec<u8>,
         residuals: Vec<u8>,
@@ -58,9 +76,12 @@ impl SeaChunk {
             frames_per_chunk: file_header.frames_per_chunk as usize,
 
             chunk_type,
+            version: file_header.version,
+            has_crc: file_header.chunk_crc,
             scale_factor_bits: encoder_settings.scale_factor_bits,
             scale_factor_frames: encoder_settings.scale_factor_frames,
-            residual_size: SeaResidualSize::from(libm::floorf(encoder_settings.residual_bits) as u8),
+            residual_size,
+            mid_side: false,
 
             lms: Vec::from(lms),
             scale_factors,
@@ -69,172 +90,35 @@ impl SeaChunk {
         }
     }
 
-    pub fn from_slice(
-        encoded: &[u8],
-        file_header: &SeaFileHeader,
-        remaining_frames: Option<usize>,
-    ) -> Result<Self, SeaError> {
-        assert!(encoded.len() <= file_header.chunk_size as usize);
-
-        // we cannot calculate last frame size in streaming mode
-        if remaining_frames.is_none() && encoded.len() < file_header.chunk_size as usize {
-            return Err(SeaError::InvalidFrame);
-        }
-
-        let chunk_type: SeaChunkType = match encoded[0] {
-            0x01 => SeaChunkType::Cbr,
-            0x02 => SeaChunkType::Vbr,
-            _ => return Err(SeaError::InvalidFrame),
-        };
-
-        let scale_factor_bits = encoded[1] >> 4;
-
-        let residual_size = SeaResidualSize::from(encoded[1] & 0b1111);
-        let scale_factor_frames = encoded[2];
-        let _reserved = encoded[3];
-
-        let mut encoded_index = 4;
-
-        let mut lms: Vec<SeaLMS> = vec![];
-        for _ in 0..file_header.channels as usize {
-            lms.push(SeaLMS::from_bytes(
-                &encoded[encoded_index..encoded_index + LMS_LEN * 4]
-                    .try_into()
-                    .unwrap(),
-            ));
-            encoded_index += LMS_LEN * 4;
-        }
-
-        let frames_in_this_chunk =
-            (file_header.frames_per_chunk as usize).min(remaining_frames.unwrap_or(usize::MAX));
-
-        let scale_factor_items = frames_in_this_chunk.div_ceil(scale_factor_frames as usize)
-            * file_header.channels as usize;
-
-        let scale_factors = {
-            let packed_scale_factor_bytes =
-                (scale_factor_items * scale_factor_bits as usize).div_ceil(8);
-
-            let packed_scale_factors =
-                &encoded[encoded_index..encoded_index + packed_scale_factor_bytes];
-            encoded_index += packed_scale_factor_bytes;
-
-            let mut unpacker = BitUnpacker::new_const_bits(scale_factor_bits);
-            unpacker.process_bytes(packed_scale_factors);
-            let mut res = unpacker.finish();
-            res.resize(scale_factor_items, 0);
-            res
-        };
-
-        let vbr_residual_sizes: Vec<u8> = if matches!(chunk_type, SeaChunkType::Vbr) {
-            let packed_vbr_residual_sizes_bytes = (scale_factor_items * 2).div_ceil(8);
-            let packed_vbr_residual_sizes =
-                &encoded[encoded_index..encoded_index + packed_vbr_residual_sizes_bytes];
-            encoded_index += packed_vbr_residual_sizes_bytes;
-
-            let mut unpacker: BitUnpacker = BitUnpacker::new_const_bits(2);
-            unpacker.process_bytes(packed_vbr_residual_sizes);
-            let mut res = unpacker.finish();
-            res.resize(scale_factor_items, 0);
-            for item in &mut res {
-                *item += residual_size as u8 - 1;
-            }
-            res
-        } else {
-            Vec::new()
-        };
-
-        let residuals: Vec<u8> = {
-            let mut unpacker = if matches!(chunk_type, SeaChunkType::Vbr) {
-                let mut bitlengths = Vec::new();
-                for vbr_chunk in vbr_residual_sizes.chunks_exact(file_header.channels as usize) {
-                    for _ in 0..scale_factor_frames {
-                        for item in vbr_chunk.iter().take(file_header.channels as usize) {
-                            bitlengths.push(*item);
-                        }
-                    }
-                }
-
-                BitUnpacker::new_var_bits(&bitlengths)
-            } else {
-                BitUnpacker::new_const_bits(residual_size as u8)
-            };
-
-            let packed_residuals_bytes = if matches!(chunk_type, SeaChunkType::Vbr) {
-                let mut residual_bits: u32 = vbr_residual_sizes
-                    [..vbr_residual_sizes.len() - file_header.channels as usize]
-                    .iter()
-                    .map(|x| *x as u32)
-                    .sum();
-
-                residual_bits *= scale_factor_frames as u32;
-
-                let last_frame_samples = frames_in_this_chunk as u32 % scale_factor_frames as u32;
-                let multiplier = if last_frame_samples == 0 {
-                    scale_factor_frames as u32
-                } else {
-                    last_frame_samples
-                };
-
-                for size in vbr_residual_sizes
-                    [(vbr_residual_sizes.len() - file_header.channels as usize)..]
-                    .iter()
-                {
-                    residual_bits += *size as u32 * multiplier;
-                }
-
-                let residual_bytes = residual_bits.div_ceil(8);
-                residual_bytes as usize
-            } else {
-                (frames_in_this_chunk * residual_size as usize * file_header.channels as usize)
-                    .div_ceil(8)
-            };
-
-            let packed_residuals = &encoded[encoded_index..encoded_index + packed_residuals_bytes];
-
-            unpacker.process_bytes(packed_residuals);
-
-            let mut res = unpacker.finish();
-            res.resize(frames_in_this_chunk * file_header.channels as usize, 0);
-            res
-        };
-
-        Ok(Self {
-            channels: file_header.channels as usize,
-            frames_per_chunk: file_header.frames_per_chunk as usize,
-
-            chunk_type,
-            scale_factor_bits,
-            scale_factor_frames,
-            residual_size,
-
-            lms,
-            scale_factors,
-            vbr_residual_sizes,
-            residuals,
-        })
-    }
-
     fn serialize_header(&self) -> [u8; 4] {
         assert!(self.scale_factor_bits > 0);
         assert!(self.scale_factor_frames > 0);
         assert_eq!(self.frames_per_chunk % self.scale_factor_frames as usize, 0);
+        assert!(!self.mid_side || self.version >= 2);
+
+        // the last byte is reserved in version 1 and holds chunk flags from version 2
+        let last_byte = match self.version {
+            1 => 0x5A,
+            _ if self.mid_side => CHUNK_FLAG_MID_SIDE,
+            _ => 0,
+        };
 
         [
             self.chunk_type as u8,
             (self.scale_factor_bits << 4) | self.residual_size as u8,
             self.scale_factor_frames,
-            0x5A,
+            last_byte,
         ]
     }
 
     fn serialize_lms(&self) -> Vec<u8> {
         assert_eq!(self.channels, self.lms.len());
 
-        self.lms
-            .iter()
-            .flat_map(|lms| lms.serialize())
-            .collect::<Vec<_>>()
+        let mut output = Vec::new();
+        for lms in self.lms.iter() {
+            lms.serialize(&mut output);
+        }
+        output
     }
 
     fn serialize_scale_factors(&self) -> Vec<u8> {
@@ -284,6 +168,10 @@ impl SeaChunk {
         let mut output = Vec::new();
 
         output.extend_from_slice(&self.serialize_header());
+        if self.has_crc {
+            // placeholder, filled in once the rest of the chunk is known
+            output.extend_from_slice(&[0; 4]);
+        }
         output.extend_from_slice(&self.serialize_lms());
         output.extend_from_slice(&self.serialize_scale_factors());
         if matches!(self.chunk_type, SeaChunkType::Vbr) {
@@ -291,6 +179,251 @@ impl SeaChunk {
         }
         output.extend_from_slice(&self.serialize_residuals());
 
+        if self.has_crc {
+            let crc = calculate_crc(&output);
+            output[4..8].copy_from_slice(&crc.to_le_bytes());
+        }
+
         output
     }
 }
+
+/// Fields of a chunk header and the section sizes following from them
+struct ChunkHeader {
+    chunk_type: SeaChunkType,
+    scale_factor_bits: u8,
+    scale_factor_frames: u8,
+    residual_size: SeaResidualSize,
+    mid_side: bool,
+    frames: usize,
+    scale_factor_items: usize,
+    lms_bytes: usize,
+    scale_factor_bytes: usize,
+    vbr_residual_size_bytes: usize,
+}
+
+/// Borrowed view of an encoded chunk. Parsing only validates the chunk and locates its
+/// sections, the packed values are read while decoding, so no allocations are needed.
+#[derive(Debug)]
+pub struct SeaChunkView<'a> {
+    pub chunk_type: SeaChunkType,
+    pub scale_factor_bits: u8,
+    pub scale_factor_frames: u8,
+    pub residual_size: SeaResidualSize,
+    /// The first two channels store mid and side instead of left and right
+    pub mid_side: bool,
+    /// Bits per sample, from the file header
+    pub sample_depth: u8,
+    /// Taps of the LMS predictors, from the file header
+    pub lms_order: usize,
+
+    /// Frames stored in this chunk
+    pub frames: usize,
+
+    pub lms: &'a [u8],
+    pub scale_factors: &'a [u8],
+    pub vbr_residual_sizes: &'a [u8],
+    pub residuals: &'a [u8],
+}
+
+impl<'a> SeaChunkView<'a> {
+    fn read_section(
+        encoded: &'a [u8],
+        encoded_index: &mut usize,
+        bytes: usize,
+    ) -> Result<&'a [u8], SeaError> {
+        let section = encoded
+            .get(*encoded_index..*encoded_index + bytes)
+            .ok_or(SeaError::INVALID_FRAME)?;
+        *encoded_index += bytes;
+        Ok(section)
+    }
+
+    /// Validates the 4 byte chunk header
+    fn parse_header(
+        chunk_header: &[u8],
+        format: &SeaFormat,
+        remaining_frames: Option<usize>,
+    ) -> Result<ChunkHeader, SeaError> {
+        let chunk_type: SeaChunkType = match chunk_header[0] {
+            0x01 => SeaChunkType::Cbr,
+            0x02 => SeaChunkType::Vbr,
+            _ => return Err(SeaError::INVALID_FRAME),
+        };
+
+        let scale_factor_bits = chunk_header[1] >> 4;
+        if !(1..=8).contains(&scale_factor_bits) {
+            return Err(SeaError::INVALID_FRAME);
+        }
+
+        let residual_size = SeaResidualSize::try_from(chunk_header[1] & 0b1111)?;
+        let scale_factor_frames = chunk_header[2];
+        if scale_factor_frames == 0 {
+            return Err(SeaError::INVALID_FRAME);
+        }
+        // reserved in version 1, flags in version 2+
+        let flags = if format.version >= 2 {
+            chunk_header[3]
+        } else {
+            0
+        };
+        if flags & !CHUNK_FLAG_MID_SIDE != 0 {
+            return Err(SeaError::INVALID_FRAME);
+        }
+        let mid_side = flags & CHUNK_FLAG_MID_SIDE != 0;
+        if mid_side && format.channels < 2 {
+            return Err(SeaError::INVALID_FRAME);
+        }
+
+        let frames = (format.frames_per_chunk as usize).min(remaining_frames.unwrap_or(usize::MAX));
+        if frames == 0 {
+            return Err(SeaError::INVALID_FRAME);
+        }
+
+        let channels = format.channels as usize;
+        let scale_factor_items = frames.div_ceil(scale_factor_frames as usize) * channels;
+        let lms_bytes = channels * format.lms_size();
+        let scale_factor_bytes = (scale_factor_items * scale_factor_bits as usize).div_ceil(8);
+        let vbr_residual_size_bytes = match chunk_type {
+            SeaChunkType::Vbr => (scale_factor_items * 2).div_ceil(8),
+            SeaChunkType::Cbr => 0,
+        };
+
+        Ok(ChunkHeader {
+            chunk_type,
+            scale_factor_bits,
+            scale_factor_frames,
+            residual_size,
+            mid_side,
+            frames,
+            scale_factor_items,
+            lms_bytes,
+            scale_factor_bytes,
+            vbr_residual_size_bytes,
+        })
+    }
+
+    /// Size of the packed residuals, the residual sizes are only read for VBR chunks
+    fn residual_bytes(
+        header: &ChunkHeader,
+        channels: usize,
+        vbr_residual_sizes: &[u8],
+    ) -> Result<usize, SeaError> {
+        if matches!(header.chunk_type, SeaChunkType::Cbr) {
+            return Ok((header.frames * header.residual_size as usize * channels).div_ceil(8));
+        }
+
+        let scale_factor_frames = header.scale_factor_frames as usize;
+        // the last scale factor block can be shorter than the others
+        let last_block_frames = match header.frames % scale_factor_frames {
+            0 => scale_factor_frames,
+            frames => frames,
+        };
+
+        let mut reader = BitReader::new(vbr_residual_sizes);
+        let mut residual_bits = 0;
+        for item_index in 0..header.scale_factor_items {
+            let size = reader.read(2) as usize + header.residual_size as usize - 1;
+            if !(1..=8).contains(&size) {
+                return Err(SeaError::INVALID_FRAME);
+            }
+            let block_frames = if item_index >= header.scale_factor_items - channels {
+                last_block_frames
+            } else {
+                scale_factor_frames
+            };
+            residual_bits += size * block_frames;
+        }
+
+        Ok(residual_bits.div_ceil(8))
+    }
+
+    /// Size of the chunk at the start of `encoded`, which is needed to find the next chunk
+    /// when chunks vary in size. When `encoded` is too short to tell, returns the number of
+    /// bytes needed to continue, call again once they are available.
+    pub fn encoded_size(
+        encoded: &[u8],
+        format: &SeaFormat,
+        remaining_frames: Option<usize>,
+    ) -> Result<usize, SeaError> {
+        let Some(chunk_header) = encoded.get(..4) else {
+            return Ok(4);
+        };
+        let header = Self::parse_header(chunk_header, format, remaining_frames)?;
+
+        let vbr_residual_sizes_start =
+            format.chunk_header_size() + header.lms_bytes + header.scale_factor_bytes;
+        let residuals_start = vbr_residual_sizes_start + header.vbr_residual_size_bytes;
+        let Some(vbr_residual_sizes) = encoded.get(vbr_residual_sizes_start..residuals_start)
+        else {
+            return Ok(residuals_start);
+        };
+
+        let residual_bytes =
+            Self::residual_bytes(&header, format.channels as usize, vbr_residual_sizes)?;
+        Ok(residuals_start + residual_bytes)
+    }
+
+    pub fn from_slice(
+        encoded: &'a [u8],
+        format: &SeaFormat,
+        remaining_frames: Option<usize>,
+    ) -> Result<Self, SeaError> {
+        if encoded.len() > format.chunk_size as usize {
+            return Err(SeaError::INVALID_FRAME);
+        }
+
+        // we cannot calculate last frame size in streaming mode
+        if remaining_frames.is_none()
+            && !format.variable_chunks()
+            && encoded.len() < format.chunk_size as usize
+        {
+            return Err(SeaError::INVALID_FRAME);
+        }
+
+        let mut encoded_index = 0;
+        let chunk_header = Self::read_section(encoded, &mut encoded_index, 4)?;
+
+        // checked before the fields are used, so damaged sizes are reported as such. The
+        // checksum excludes the padding of fixed size chunks, the size without it is only
+        // trusted when the checksum matches.
+        if format.chunk_crc() {
+            let crc_bytes = Self::read_section(encoded, &mut encoded_index, 4)?;
+            let stored_crc = u32::from_le_bytes(crc_bytes.try_into().unwrap());
+            let verified = calculate_crc(encoded) == stored_crc
+                || Self::encoded_size(encoded, format, remaining_frames)
+                    .ok()
+                    .and_then(|chunk_size| encoded.get(..chunk_size))
+                    .is_some_and(|chunk| calculate_crc(chunk) == stored_crc);
+            if !verified {
+                return Err(SeaError::CHECKSUM_MISMATCH);
+            }
+        }
+
+        let header = Self::parse_header(chunk_header, format, remaining_frames)?;
+        let lms = Self::read_section(encoded, &mut encoded_index, header.lms_bytes)?;
+        let scale_factors =
+            Self::read_section(encoded, &mut encoded_index, header.scale_factor_bytes)?;
+        let vbr_residual_sizes =
+            Self::read_section(encoded, &mut encoded_index, header.vbr_residual_size_bytes)?;
+        let packed_residuals_bytes =
+            Self::residual_bytes(&header, format.channels as usize, vbr_residual_sizes)?;
+
+        let residuals = Self::read_section(encoded, &mut encoded_index, packed_residuals_bytes)?;
+
+        Ok(Self {
+            chunk_type: header.chunk_type,
+            scale_factor_bits: header.scale_factor_bits,
+            scale_factor_frames: header.scale_factor_frames,
+            residual_size: header.residual_size,
+            mid_side: header.mid_side,
+            sample_depth: format.sample_depth(),
+            lms_order: format.lms_order(),
+            frames: header.frames,
+            lms,
+            scale_factors,
+            vbr_residual_sizes,
+            residuals,
+        })
+    }
+}
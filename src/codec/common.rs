@@ -1,13 +1,22 @@
-use alloc::vec;
+#[cfg(feature = "alloc")]
 use alloc::vec::Vec;
 
+use core::fmt;
+
 use crate::cursor::Cursor;
 
 pub const SEAC_MAGIC: u32 = u32::from_be_bytes(*b"seac"); // 0x73 0x65 0x61 0x63
 
+/// Largest sample value for the given sample depth
 #[inline(always)]
-pub fn clamp_i16(v: i32) -> i16 {
-    v.clamp(i16::MIN as i32, i16::MAX as i32) as i16
+pub const fn sample_max(sample_depth: u8) -> i32 {
+    (1 << (sample_depth - 1)) - 1
+}
+
+/// Clamps to the range of samples with `sample_max` as the largest value
+#[inline(always)]
+pub fn clamp_sample(v: i32, sample_max: i32) -> i32 {
+    v.clamp(-sample_max - 1, sample_max)
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -22,22 +31,33 @@ pub enum SeaResidualSize {
     Eight = 8,
 }
 
-impl SeaResidualSize {
+impl TryFrom<u8> for SeaResidualSize {
+    type Error = SeaError;
+
     #[inline(always)]
-    pub fn from(len: u8) -> Self {
+    fn try_from(len: u8) -> Result<Self, SeaError> {
         match len {
-            1 => SeaResidualSize::One,
-            2 => SeaResidualSize::Two,
-            3 => SeaResidualSize::Three,
-            4 => SeaResidualSize::Four,
-            5 => SeaResidualSize::Five,
-            6 => SeaResidualSize::Six,
-            7 => SeaResidualSize::Seven,
-            8 => SeaResidualSize::Eight,
-            _ => panic!("Invalid residual length"),
+            1 => Ok(SeaResidualSize::One),
+            2 => Ok(SeaResidualSize::Two),
+            3 => Ok(SeaResidualSize::Three),
+            4 => Ok(SeaResidualSize::Four),
+            5 => Ok(SeaResidualSize::Five),
+            6 => Ok(SeaResidualSize::Six),
+            7 => Ok(SeaResidualSize::Seven),
+            8 => Ok(SeaResidualSize::Eight),
+            _ => Err(SeaError::INVALID_FRAME),
         }
     }
+}
+
+impl SeaResidualSize {
+    #[cfg(feature = "alloc")]
+    #[inline(always)]
+    pub fn from(len: u8) -> Self {
+        Self::try_from(len).expect("Invalid residual length")
+    }
 
+    #[cfg(feature = "alloc")]
     #[inline(always)]
     pub fn to_binary_combinations(self) -> usize {
         match self {
@@ -57,17 +77,122 @@ impl SeaResidualSize {
 pub enum SeaError {
     ReadError,
     InvalidParameters,
-    InvalidFile,
-    InvalidFrame,
+    /// An `EncoderSettings` field is out of range, see `EncoderSettings::validate`
+    InvalidSettings {
+        field: &'static str,
+        reason: &'static str,
+    },
+    /// The file header or the metadata is malformed
+    InvalidFile {
+        reason: &'static str,
+    },
+    /// A chunk is malformed. `chunk` is its index in the stream and `offset` the byte
+    /// offset it starts at in the decoder input. Sync streams count from the join point.
+    InvalidFrame {
+        chunk: u64,
+        offset: u64,
+    },
     EncoderClosed,
-    UnsupportedVersion,
+    UnsupportedVersion {
+        version: u8,
+    },
+    /// The header signals features this decoder does not know
+    UnsupportedFlags {
+        flags: u8,
+    },
     TooManyFrames,
     MetadataTooLarge,
     EndOfFile,
+    SeekNotSupported,
+    /// The CRC-32 of a chunk does not match its contents, positioned like `InvalidFrame`
+    ChecksumMismatch {
+        chunk: u64,
+        offset: u64,
+    },
+    /// The dequantization tables passed to the embedded decoder do not fit the next chunk,
+    /// which needs tables for these scale factor bits and sample depth. The chunk is not
+    /// consumed, so decoding can be retried with matching tables.
+    TablesMismatch {
+        scale_factor_bits: u8,
+        sample_depth: u8,
+    },
     #[cfg(feature = "std")]
     IoError(std::io::Error),
 }
 
+impl SeaError {
+    // the chunk parser does not know where the chunk is, decoders add it with `at_chunk`
+    pub(crate) const INVALID_FRAME: Self = Self::InvalidFrame {
+        chunk: 0,
+        offset: 0,
+    };
+    pub(crate) const CHECKSUM_MISMATCH: Self = Self::ChecksumMismatch {
+        chunk: 0,
+        offset: 0,
+    };
+
+    /// Sets the position of chunk errors, other errors are returned unchanged
+    pub(crate) fn at_chunk(self, chunk: u64, offset: u64) -> Self {
+        match self {
+            Self::InvalidFrame { .. } => Self::InvalidFrame { chunk, offset },
+            Self::ChecksumMismatch { .. } => Self::ChecksumMismatch { chunk, offset },
+            err => err,
+        }
+    }
+}
+
+impl fmt::Display for SeaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SeaError::ReadError => write!(f, "read error"),
+            SeaError::InvalidParameters => write!(f, "invalid parameters"),
+            SeaError::InvalidSettings { field, reason } => {
+                write!(f, "invalid encoder settings: {} {}", field, reason)
+            }
+            SeaError::InvalidFile { reason } => write!(f, "invalid file: {}", reason),
+            SeaError::InvalidFrame { chunk, offset } => {
+                write!(f, "invalid chunk {} at byte offset {}", chunk, offset)
+            }
+            SeaError::EncoderClosed => write!(f, "encoder is already finalized"),
+            SeaError::UnsupportedVersion { version } => {
+                write!(f, "unsupported file version {}", version)
+            }
+            SeaError::UnsupportedFlags { flags } => {
+                write!(f, "unsupported header flags {:#04x}", flags)
+            }
+            SeaError::TooManyFrames => write!(f, "frame count does not fit the file header"),
+            SeaError::MetadataTooLarge => write!(f, "metadata is too large"),
+            SeaError::EndOfFile => write!(f, "unexpected end of input"),
+            SeaError::SeekNotSupported => write!(f, "input does not support seeking"),
+            SeaError::ChecksumMismatch { chunk, offset } => write!(
+                f,
+                "checksum mismatch in chunk {} at byte offset {}",
+                chunk, offset
+            ),
+            SeaError::TablesMismatch {
+                scale_factor_bits,
+                sample_depth,
+            } => write!(
+                f,
+                "dequantization tables do not match the chunk, which needs {}-bit scale factors at {} bits per sample",
+                scale_factor_bits, sample_depth
+            ),
+            #[cfg(feature = "std")]
+            SeaError::IoError(err) => write!(f, "I/O error: {}", err),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SeaError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SeaError::IoError(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
 #[cfg(feature = "std")]
 impl From<std::io::Error> for SeaError {
     fn from(error: std::io::Error) -> Self {
@@ -106,11 +231,19 @@ pub fn read_u32_le(reader: &mut Cursor) -> Result<u32, SeaError> {
     Ok(u32::from_le_bytes(data))
 }
 
-pub fn read_max_or_zero(reader: &mut Cursor, at_least_bytes: usize) -> Result<Vec<u8>, SeaError> {
-    let mut buffer = vec![0u8; at_least_bytes];
+#[inline(always)]
+pub fn read_u64_le(reader: &mut Cursor) -> Result<u64, SeaError> {
+    let data = read_bytes(reader)?;
+    Ok(u64::from_le_bytes(data))
+}
+
+#[cfg(feature = "alloc")]
+/// Fills `buffer` from the reader, stopping early at the end of the input.
+/// Returns the number of bytes read.
+pub fn read_max(reader: &mut Cursor, buffer: &mut [u8]) -> Result<usize, SeaError> {
     let mut total_bytes_read = 0;
 
-    while total_bytes_read < at_least_bytes {
+    while total_bytes_read < buffer.len() {
         let bytes_read = reader.read(&mut buffer[total_bytes_read..])?;
 
         // EOF
@@ -121,20 +254,20 @@ pub fn read_max_or_zero(reader: &mut Cursor, at_least_bytes: usize) -> Result<Ve
         total_bytes_read += bytes_read;
     }
 
-    if total_bytes_read == 0 {
-        return Ok(Vec::new());
-    }
-
-    Ok(buffer[..total_bytes_read].to_vec())
+    Ok(total_bytes_read)
 }
 
+#[cfg(feature = "alloc")]
 #[derive(Debug)]
 pub struct EncodedSamples {
     pub scale_factors: Vec<u8>,
     pub residuals: Vec<u8>,
     pub residual_bits: Vec<u8>,
+    /// Stored in the chunk header, VBR residual sizes are relative to it
+    pub residual_size: SeaResidualSize,
 }
 
+#[cfg(feature = "alloc")]
 pub trait SeaEncoderTrait {
-    fn encode(&mut self, input_slice: &[i16]) -> EncodedSamples;
+    fn encode(&mut self, input_slice: &[i32]) -> EncodedSamples;
 }
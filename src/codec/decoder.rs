@@ -1,85 +1,202 @@
-use alloc::vec::Vec;
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
+#[cfg(feature = "alloc")]
+use super::dqt::SeaDequantTab;
+use super::{
+    bits::BitReader,
+    chunk::{SeaChunkType, SeaChunkView},
+    common::{clamp_sample, sample_max},
+    lms::SeaLMS,
+    stereo::to_left_right,
+};
+#[cfg(all(feature = "alloc", feature = "static-tables"))]
+use crate::embedded::{STATIC_TABLES_3, STATIC_TABLES_4, STATIC_TABLES_5};
+
+/// Source of dequantized residual values
+pub trait DequantLookup {
+    fn dequantize(&self, residual_size: u8, scale_factor: u8, quantized: u8) -> i32;
+}
 
-use super::{chunk::SeaChunk, common::clamp_i16, dqt::SeaDequantTab};
+/// Per channel decoder state, reused between chunks
+#[derive(Debug, Clone)]
+pub struct ChannelState {
+    lms: SeaLMS,
+    scale_factor: u8,
+    residual_size: u8,
+}
 
-pub struct Decoder {
-    channels: usize,
-    scale_factor_bits: usize,
+impl ChannelState {
+    pub const fn new() -> Self {
+        Self {
+            lms: SeaLMS::new(),
+            scale_factor: 0,
+            residual_size: 0,
+        }
+    }
+}
 
-    dequant_tab: SeaDequantTab,
+/// Sample type decoders can write, converted from a decoded sample of `sample_depth` bits
+pub trait OutputSample: Copy + Default {
+    fn from_sample(sample: i32, sample_depth: u8) -> Self;
 }
 
-impl Decoder {
-    pub fn init(channels: usize, scale_factor_bits: usize) -> Self {
-        Self {
-            channels,
-            scale_factor_bits,
+impl OutputSample for i16 {
+    /// Drops the bits below 16-bit resolution
+    #[inline(always)]
+    fn from_sample(sample: i32, sample_depth: u8) -> Self {
+        (sample >> (sample_depth - 16)) as i16
+    }
+}
 
-            dequant_tab: SeaDequantTab::init(scale_factor_bits),
-        }
+impl OutputSample for i32 {
+    /// Keeps the sample depth of the stream
+    #[inline(always)]
+    fn from_sample(sample: i32, _sample_depth: u8) -> Self {
+        sample
     }
+}
 
-    pub fn decode_cbr(&self, chunk: &SeaChunk, output: &mut Vec<i16>) {
-        assert_eq!(chunk.scale_factor_bits as usize, self.scale_factor_bits);
+impl OutputSample for f32 {
+    /// Normalised to [-1, 1)
+    #[inline(always)]
+    fn from_sample(sample: i32, sample_depth: u8) -> Self {
+        sample as f32 / (1 << (sample_depth - 1)) as f32
+    }
+}
 
-        output.reserve(chunk.frames_per_chunk * self.channels);
+/// Destination of decoded samples, addressed by frame within the chunk and channel
+pub trait SampleWriter {
+    fn write(&mut self, frame: usize, channel: usize, sample: i32);
+}
 
-        let mut lms = chunk.lms.clone();
+/// Writes interleaved samples into a single buffer
+pub struct InterleavedWriter<'a, S> {
+    pub output: &'a mut [S],
+    pub channels: usize,
+    pub sample_depth: u8,
+}
 
-        let dqts: &Vec<Vec<i32>> = self.dequant_tab.get_dqt(chunk.residual_size as usize);
+impl<S: OutputSample> SampleWriter for InterleavedWriter<'_, S> {
+    #[inline(always)]
+    fn write(&mut self, frame: usize, channel: usize, sample: i32) {
+        self.output[frame * self.channels + channel] = S::from_sample(sample, self.sample_depth);
+    }
+}
+
+/// Writes each channel into its own buffer
+pub struct PlanarWriter<'a, 'b, S> {
+    pub output: &'a mut [&'b mut [S]],
+    pub sample_depth: u8,
+}
 
-        for (scale_factor_index, subchunk_residuals) in chunk
-            .residuals
-            .chunks(self.channels * chunk.scale_factor_frames as usize)
-            .enumerate()
-        {
-            let scale_factors = &chunk.scale_factors[scale_factor_index * self.channels..];
+impl<S: OutputSample> SampleWriter for PlanarWriter<'_, '_, S> {
+    #[inline(always)]
+    fn write(&mut self, frame: usize, channel: usize, sample: i32) {
+        self.output[channel][frame] = S::from_sample(sample, self.sample_depth);
+    }
+}
 
-            for channel_residuals in subchunk_residuals.chunks(self.channels) {
-                for (channel_index, residual) in channel_residuals.iter().enumerate() {
-                    let scale_factor = scale_factors[channel_index];
-                    let predicted = lms[channel_index].predict();
-                    let quantized: usize = *residual as usize;
-                    let dequantized = dqts[scale_factor as usize][quantized];
-                    let reconstructed = clamp_i16(predicted + dequantized);
-                    output.push(reconstructed);
-                    lms[channel_index].update(reconstructed, dequantized);
+/// Decodes a chunk into `output`, which has to have room for `chunk.frames` frames.
+/// Returns the number of samples written.
+pub fn decode_chunk(
+    chunk: &SeaChunkView,
+    dequant: &impl DequantLookup,
+    channels: &mut [ChannelState],
+    output: &mut impl SampleWriter,
+) -> usize {
+    let lms_size = chunk.lms.len() / channels.len();
+    for (channel, lms_bytes) in channels.iter_mut().zip(chunk.lms.chunks_exact(lms_size)) {
+        channel.lms = SeaLMS::from_bytes(lms_bytes, chunk.sample_depth, chunk.lms_order);
+    }
+
+    let sample_max = sample_max(chunk.sample_depth);
+
+    let mut scale_factor_reader = BitReader::new(chunk.scale_factors);
+    let mut vbr_residual_size_reader = BitReader::new(chunk.vbr_residual_sizes);
+    let mut residual_reader = BitReader::new(chunk.residuals);
+
+    let scale_factor_frames = chunk.scale_factor_frames as usize;
+
+    for subchunk_start in (0..chunk.frames).step_by(scale_factor_frames) {
+        for channel in channels.iter_mut() {
+            channel.scale_factor = scale_factor_reader.read(chunk.scale_factor_bits);
+            channel.residual_size = match chunk.chunk_type {
+                SeaChunkType::Cbr => chunk.residual_size as u8,
+                SeaChunkType::Vbr => {
+                    vbr_residual_size_reader.read(2) + chunk.residual_size as u8 - 1
                 }
-            }
+            };
         }
-    }
 
-    pub fn decode_vbr(&self, chunk: &SeaChunk, output: &mut Vec<i16>) {
-        assert_eq!(chunk.scale_factor_bits as usize, self.scale_factor_bits);
-
-        output.reserve(chunk.frames_per_chunk * self.channels);
-
-        let mut lms = chunk.lms.clone();
-
-        let dqts: &Vec<Vec<Vec<i32>>> = &(1..=8)
-            .map(|i| self.dequant_tab.get_dqt(i).clone())
-            .collect();
-
-        for (scale_factor_index, subchunk_residuals) in chunk
-            .residuals
-            .chunks(self.channels * chunk.scale_factor_frames as usize)
-            .enumerate()
-        {
-            let scale_factors = &chunk.scale_factors[scale_factor_index * self.channels..];
-            let vbr_residuals = &chunk.vbr_residual_sizes[scale_factor_index * self.channels..];
-
-            for channel_residuals in subchunk_residuals.chunks(self.channels) {
-                for (channel_index, residual) in channel_residuals.iter().enumerate() {
-                    let residual_size: usize = vbr_residuals[channel_index] as usize;
-                    let scale_factor = scale_factors[channel_index];
-                    let predicted = lms[channel_index].predict();
-                    let quantized: usize = *residual as usize;
-                    let dequantized = dqts[residual_size - 1][scale_factor as usize][quantized];
-                    let reconstructed = clamp_i16(predicted + dequantized);
-                    output.push(reconstructed);
-                    lms[channel_index].update(reconstructed, dequantized);
+        let subchunk_end = (subchunk_start + scale_factor_frames).min(chunk.frames);
+        for frame in subchunk_start..subchunk_end {
+            let mut mid = 0;
+            for (channel_index, channel) in channels.iter_mut().enumerate() {
+                let quantized = residual_reader.read(channel.residual_size);
+                let dequantized =
+                    dequant.dequantize(channel.residual_size, channel.scale_factor, quantized);
+
+                // corrupt states and residuals saturate instead of overflowing
+                let reconstructed = clamp_sample(
+                    channel.lms.predict().saturating_add(dequantized),
+                    sample_max,
+                );
+                channel.lms.update(reconstructed, dequantized);
+
+                match channel_index {
+                    // written together with the side sample
+                    0 if chunk.mid_side => mid = reconstructed,
+                    1 if chunk.mid_side => {
+                        let (left, right) = to_left_right(mid, reconstructed);
+                        output.write(frame, 0, clamp_sample(left, sample_max));
+                        output.write(frame, 1, clamp_sample(right, sample_max));
+                    }
+                    _ => output.write(frame, channel_index, reconstructed),
                 }
             }
         }
     }
+
+    chunk.frames * channels.len()
+}
+
+#[cfg(feature = "alloc")]
+pub struct Decoder {
+    // only computed for scale factor sizes without static tables
+    dequant_tab: Option<SeaDequantTab>,
+    channels: Vec<ChannelState>,
+}
+
+#[cfg(feature = "alloc")]
+impl Decoder {
+    pub fn init(channels: usize) -> Self {
+        Self {
+            dequant_tab: None,
+            channels: vec![ChannelState::new(); channels],
+        }
+    }
+
+    /// Decodes a chunk into `output`, which has to hold at least `chunk.frames` frames.
+    /// Returns the number of samples written.
+    pub fn decode(&mut self, chunk: &SeaChunkView, output: &mut impl SampleWriter) -> usize {
+        // chunks are allowed to use different scale factor sizes
+        let scale_factor_bits = chunk.scale_factor_bits as usize;
+
+        #[cfg(feature = "static-tables")]
+        match scale_factor_bits {
+            _ if chunk.sample_depth != 16 => (),
+            3 => return decode_chunk(chunk, &STATIC_TABLES_3, &mut self.channels, output),
+            4 => return decode_chunk(chunk, &STATIC_TABLES_4, &mut self.channels, output),
+            5 => return decode_chunk(chunk, &STATIC_TABLES_5, &mut self.channels, output),
+            _ => (),
+        }
+
+        let dequant_tab = self
+            .dequant_tab
+            .get_or_insert_with(|| SeaDequantTab::init(scale_factor_bits, chunk.sample_depth));
+        dequant_tab.set_scalefactor_bits(scale_factor_bits);
+
+        decode_chunk(chunk, dequant_tab, &mut self.channels, output)
+    }
 }
@@ -0,0 +1,79 @@
+// Dequantization values, shared with the build script that generates the static tables.
+// Only depends on `core` and `libm`.
+
+/// Number of values per scale factor: residual sizes 1 to 8, stored after each other
+pub const DQT_ROW_LEN: usize = 2 + 4 + 8 + 16 + 32 + 64 + 128 + 256;
+
+// scale_factors along with residuals should cover all potential values
+// we try to calcualte an exponent for max scalefactor that is efficient given the range ot residuals
+// theoretically [12, 11, 10, 9, 8, 7] should be fine, but these numbers perform better over a diverse dataset
+pub static IDEAL_POW_FACTOR: [f32; 8] = [12.0, 11.65, 11.20, 10.58, 9.64, 8.75, 7.66, 6.63]; // were found experimentally
+
+/// Value of the scale factor at `index`, which is below `1 << scale_factor_bits`.
+/// Every bit of sample depth above 16 doubles the largest scale factor.
+pub fn scale_factor_value(
+    scale_factor_bits: usize,
+    residual_bits: usize,
+    sample_depth: u8,
+    index: usize,
+) -> i32 {
+    let max_exponent = IDEAL_POW_FACTOR[residual_bits - 1] + (sample_depth - 16) as f32;
+    let power_factor = max_exponent / (scale_factor_bits as f32);
+    libm::powf((index + 1) as f32, power_factor) as i32
+}
+
+/// Magnitude of the `index`-th dequantization step, relative to the scale factor
+fn dqt_step(residual_bits: usize, index: usize) -> f32 {
+    match residual_bits {
+        1 => return 2.0,
+        2 => return [1.115, 4.0][index],
+        _ => (),
+    }
+
+    let start: f32 = 0.75f32;
+    let steps = 1 << (residual_bits - 1);
+    let end = ((1 << residual_bits) - 1) as f32;
+
+    if index == 0 {
+        return start;
+    }
+    if index == steps - 1 {
+        return end;
+    }
+
+    let step = (end - start) / (steps - 1) as f32;
+    0.5 + index as f32 * libm::floorf(step)
+}
+
+/// Fills the `1 << residual_bits` dequantized values of a scale factor
+pub fn fill_dqt_row(row: &mut [i32], scale_factor: i32, residual_bits: usize) {
+    // zig zag pattern decreases quantization error
+    for (index, pair) in row.chunks_exact_mut(2).enumerate() {
+        let val = libm::roundf(scale_factor as f32 * dqt_step(residual_bits, index)) as i32;
+        pair[0] = val;
+        pair[1] = -val;
+    }
+}
+
+/// Offset of the values for `residual_bits` within a row of `DQT_ROW_LEN` values
+#[inline(always)]
+pub const fn residual_offset(residual_bits: usize) -> usize {
+    (1 << residual_bits) - 2
+}
+
+/// Fills the values of the scale factor at `index` for every residual size
+pub fn fill_dqt_row_all_residuals(
+    row: &mut [i32; DQT_ROW_LEN],
+    scale_factor_bits: usize,
+    sample_depth: u8,
+    index: usize,
+) {
+    for residual_bits in 1..=8 {
+        let offset = residual_offset(residual_bits);
+        fill_dqt_row(
+            &mut row[offset..offset + (1 << residual_bits)],
+            scale_factor_value(scale_factor_bits, residual_bits, sample_depth, index),
+            residual_bits,
+        );
+    }
+}
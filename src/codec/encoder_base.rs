@@ -4,7 +4,7 @@ use alloc::vec;
 use alloc::vec::Vec;
 
 use super::{
-    common::{clamp_i16, SeaResidualSize},
+    common::{clamp_sample, sample_max, SeaResidualSize},
     dqt::SeaDequantTab,
     lms::SeaLMS,
     qt::SeaQuantTab,
@@ -13,6 +13,7 @@ use super::{
 pub struct EncoderBase {
     channels: usize,
     scale_factor_bits: usize,
+    sample_max: i32,
 
     current_residuals: Vec<u8>,
     prev_scalefactor: Vec<i32>,
@@ -22,48 +23,44 @@ pub struct EncoderBase {
     pub lms: Vec<SeaLMS>,
 }
 
+/// Divides by a scale factor using its reciprocal with `reciprocal_bits` of precision
 #[inline(always)]
-pub fn sea_div(v: i32, scalefactor_reciprocal: i64) -> i32 {
-    let n = (v as i64 * scalefactor_reciprocal + (1 << 15)) >> 16;
+pub fn sea_div(v: i32, scalefactor_reciprocal: i64, reciprocal_bits: u32) -> i32 {
+    let n = (v as i64 * scalefactor_reciprocal + (1 << (reciprocal_bits - 1))) >> reciprocal_bits;
     (n + (v.signum() as i64 - n.signum())) as i32
 }
 
 impl EncoderBase {
-    #[inline]
-    fn lms_prediction(lms: &SeaLMS) -> Option<i32> {
-        let sum = lms
-            .weights
-            .iter()
-            .zip(lms.history)
-            .try_fold(0i64, |sum, (&weight, history)| {
-                sum.checked_add(weight as i64 * history as i64)
-            })?;
-        i32::try_from(sum).ok().map(|value| value >> 13)
-    }
-
     #[inline]
     fn lms_update_is_safe(lms: &SeaLMS, residual: i32) -> bool {
-        let delta = residual >> 4;
-        lms.history
+        let order = lms.order();
+        let delta = lms.update_delta(residual);
+        lms.history[..order]
             .iter()
-            .zip(lms.weights)
-            .all(|(&history, weight)| {
+            .zip(&lms.weights[..order])
+            .all(|(&history, &weight)| {
                 let adjustment = if history < 0 { -delta } else { delta };
                 weight.checked_add(adjustment).is_some()
             })
     }
 
-    pub fn new(channels: usize, scale_factor_bits: usize) -> Self {
+    pub fn new(
+        channels: usize,
+        scale_factor_bits: usize,
+        sample_depth: u8,
+        lms_order: usize,
+    ) -> Self {
         Self {
             channels,
             scale_factor_bits,
+            sample_max: sample_max(sample_depth),
 
             current_residuals: Vec::new(),
             prev_scalefactor: vec![0; channels],
             best_residual_bits: Vec::new(),
-            dequant_tab: SeaDequantTab::init(scale_factor_bits),
+            dequant_tab: SeaDequantTab::init(scale_factor_bits, sample_depth),
             quant_tab: SeaQuantTab::init(),
-            lms: SeaLMS::init_vec(channels as u32),
+            lms: SeaLMS::init_vec(channels as u32, sample_depth, lms_order),
         }
     }
 
@@ -72,7 +69,7 @@ impl EncoderBase {
         &self,
         channels: usize,
         dequant_tab: &[i32],
-        samples: &[i16],
+        samples: &[i32],
         scalefactor: i32,
         lms: &mut SeaLMS,
         best_rank: u64, // provided as optimization, can be u64::MAX if omitted
@@ -89,21 +86,23 @@ impl EncoderBase {
 
         let quant_tab_offset = clamp_limit + quant_tab.offsets[residual_size as usize] as i32;
 
-        for (index, sample_i16) in samples.iter().step_by(channels).enumerate() {
-            let sample = *sample_i16 as i32;
-            let Some(predicted) = Self::lms_prediction(lms) else {
+        let reciprocal_bits = self.dequant_tab.reciprocal_bits();
+
+        for (index, &sample) in samples.iter().step_by(channels).enumerate() {
+            let Some(predicted) = lms.checked_predict() else {
                 return u64::MAX;
             };
             let residual = sample - predicted;
             let scaled = sea_div(
                 residual,
                 scalefactor_reciprocals[scalefactor as usize] as i64,
+                reciprocal_bits,
             );
             let clamped = scaled.clamp(-clamp_limit, clamp_limit);
             let quantized = quant_tab.quant_tab[(quant_tab_offset + clamped) as usize];
 
             let dequantized = dequant_tab[quantized as usize];
-            let reconstructed = clamp_i16(predicted + dequantized);
+            let reconstructed = clamp_sample(predicted + dequantized, self.sample_max);
 
             let error: i64 = sample as i64 - reconstructed as i64;
 
@@ -133,7 +132,7 @@ impl EncoderBase {
         channels: usize,
         dequant_tab: &[Vec<i32>],
         scalefactor_reciprocals: &[i32],
-        samples: &[i16],
+        samples: &[i32],
         prev_scalefactor: i32, // provided as optimization, can be 0
         ref_lms: &SeaLMS,
         residual_size: SeaResidualSize,
@@ -143,7 +142,8 @@ impl EncoderBase {
     ) -> (u64, SeaLMS, i32) {
         let mut best_rank: u64 = u64::MAX;
 
-        let mut best_lms = SeaLMS::new();
+        // kept when every scale factor overflows the decoder
+        let mut best_lms = ref_lms.clone();
         let mut best_scalefactor: i32 = 0;
 
         let mut current_lms: SeaLMS = ref_lms.clone();
@@ -183,7 +183,7 @@ impl EncoderBase {
 
     pub fn get_residuals_for_chunk(
         &mut self,
-        samples: &[i16],
+        samples: &[i32],
         residual_size: &[SeaResidualSize],
         scale_factors: &mut [u8],
         residuals: &mut [u8],
@@ -204,7 +204,7 @@ impl EncoderBase {
     /// historical LMS-stability penalty through `get_residuals_for_chunk`.
     pub fn get_residuals_for_chunk_exact_sse(
         &mut self,
-        samples: &[i16],
+        samples: &[i32],
         residual_size: &[SeaResidualSize],
         scale_factors: &mut [u8],
         residuals: &mut [u8],
@@ -222,7 +222,7 @@ impl EncoderBase {
 
     fn get_residuals_for_chunk_with_rank(
         &mut self,
-        samples: &[i16],
+        samples: &[i32],
         residual_size: &[SeaResidualSize],
         scale_factors: &mut [u8],
         residuals: &mut [u8],
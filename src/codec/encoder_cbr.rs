@@ -6,6 +6,7 @@ use crate::encoder::EncoderSettings;
 use super::{
     common::{EncodedSamples, SeaEncoderTrait, SeaResidualSize},
     encoder_base::EncoderBase,
+    encoder_vbr_beam::{BeamPeriod, ResidualBeamSearch},
     file::SeaFileHeader,
     lms::SeaLMS,
 };
@@ -15,6 +16,7 @@ pub struct CbrEncoder {
     residual_size: SeaResidualSize,
     scale_factor_frames: usize,
     base_encoder: EncoderBase,
+    beam: Option<ResidualBeamSearch>,
 }
 
 impl CbrEncoder {
@@ -26,17 +28,31 @@ impl CbrEncoder {
             base_encoder: EncoderBase::new(
                 file_header.channels as usize,
                 encoder_settings.scale_factor_bits as usize,
+                file_header.sample_depth,
+                file_header.lms_order as usize,
             ),
+            beam: (encoder_settings.vbr_residual_beam_width > 0).then(|| {
+                ResidualBeamSearch::new(
+                    file_header.channels as usize,
+                    encoder_settings.scale_factor_bits as usize,
+                    file_header.sample_depth,
+                    encoder_settings.vbr_residual_beam_width as usize,
+                )
+            }),
         }
     }
 
     pub fn get_lms(&self) -> &Vec<SeaLMS> {
         &self.base_encoder.lms
     }
+
+    pub fn get_lms_mut(&mut self) -> &mut [SeaLMS] {
+        &mut self.base_encoder.lms
+    }
 }
 
 impl SeaEncoderTrait for CbrEncoder {
-    fn encode(&mut self, samples: &[i16]) -> EncodedSamples {
+    fn encode(&mut self, samples: &[i32]) -> EncodedSamples {
         let mut scale_factors =
             vec![
                 0u8;
@@ -51,20 +67,72 @@ impl SeaEncoderTrait for CbrEncoder {
 
         let residual_sizes = vec![self.residual_size; self.channels];
 
+        let Some(beam) = self.beam.as_ref() else {
+            for (slice_index, input_slice) in samples.chunks(slice_size).enumerate() {
+                self.base_encoder.get_residuals_for_chunk(
+                    input_slice,
+                    &residual_sizes,
+                    &mut scale_factors[slice_index * self.channels..],
+                    &mut residuals[slice_index * slice_size..],
+                    &mut ranks,
+                );
+            }
+
+            return EncodedSamples {
+                scale_factors,
+                residuals,
+                residual_bits: vec![],
+                residual_size: self.residual_size,
+            };
+        };
+
+        // the beam search starts from the greedy result of every period
+        let mut lms = self.base_encoder.lms.clone();
+        let mut greedy_factors = vec![0u8; self.channels];
+        let mut greedy_residuals = vec![0u8; slice_size];
+        let width = self.residual_size as usize;
+
         for (slice_index, input_slice) in samples.chunks(slice_size).enumerate() {
-            self.base_encoder.get_residuals_for_chunk(
+            self.base_encoder.lms.clone_from(&lms);
+            self.base_encoder.get_residuals_for_chunk_exact_sse(
                 input_slice,
                 &residual_sizes,
-                &mut scale_factors[slice_index * self.channels..],
-                &mut residuals[slice_index * slice_size..],
+                &mut greedy_factors,
+                &mut greedy_residuals,
                 &mut ranks,
             );
+            let frames = input_slice.len() / self.channels;
+            for channel in 0..self.channels {
+                let period = BeamPeriod {
+                    input: input_slice,
+                    start: 0,
+                    frames,
+                    channel,
+                    initial_lms: &lms[channel],
+                    fallback_lms: &self.base_encoder.lms[channel],
+                    fallback_residuals: &greedy_residuals,
+                };
+                let (factor, next_lms, symbols) = beam.refine_channel(
+                    &period,
+                    greedy_factors[channel] as usize,
+                    width,
+                    ranks[channel],
+                );
+                scale_factors[slice_index * self.channels + channel] = factor as u8;
+                lms[channel] = next_lms;
+                for frame in 0..frames {
+                    residuals[slice_index * slice_size + frame * self.channels + channel] =
+                        symbols[frame];
+                }
+            }
         }
+        self.base_encoder.lms = lms;
 
         EncodedSamples {
             scale_factors,
             residuals,
             residual_bits: vec![],
+            residual_size: self.residual_size,
         }
     }
 }
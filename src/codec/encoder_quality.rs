@@ -0,0 +1,221 @@
+use alloc::{vec, vec::Vec};
+
+use crate::encoder::{EncoderSettings, QualityTarget};
+
+use super::{
+    common::{sample_max, EncodedSamples, SeaEncoderTrait, SeaResidualSize},
+    encoder_base::EncoderBase,
+    file::SeaFileHeader,
+    lms::SeaLMS,
+};
+
+const MAX_RESIDUAL_SIZE: u8 = 8;
+
+/// Quality target encoder.
+///
+/// Every period is analysed with growing residual sizes until its squared error is within
+/// the budget of the target. A chunk stores the sizes relative to its base size, within
+/// -1..=2, so the sizes are raised to fit that range before the chunk is encoded. Raising
+/// a size changes the predictor state of later periods, these are checked again while
+/// encoding and get a larger size if they miss the target.
+pub struct QualityEncoder {
+    channels: usize,
+    scale_factor_frames: usize,
+    target: QualityTarget,
+    // squared amplitude of a full scale sample, the reference of PSNR targets
+    full_scale_energy: f64,
+    base_encoder: EncoderBase,
+}
+
+impl QualityEncoder {
+    pub fn new(
+        file_header: &SeaFileHeader,
+        encoder_settings: &EncoderSettings,
+        target: QualityTarget,
+    ) -> Self {
+        let full_scale = sample_max(file_header.sample_depth) as f64 + 1.0;
+        QualityEncoder {
+            channels: file_header.channels as usize,
+            scale_factor_frames: encoder_settings.scale_factor_frames as usize,
+            target,
+            full_scale_energy: full_scale * full_scale,
+            base_encoder: EncoderBase::new(
+                file_header.channels as usize,
+                encoder_settings.scale_factor_bits as usize,
+                file_header.sample_depth,
+                file_header.lms_order as usize,
+            ),
+        }
+    }
+
+    pub fn get_lms(&self) -> &Vec<SeaLMS> {
+        &self.base_encoder.lms
+    }
+
+    pub fn get_lms_mut(&mut self) -> &mut [SeaLMS] {
+        &mut self.base_encoder.lms
+    }
+
+    /// Largest squared error of a channel in a period that meets the target
+    fn error_budget(&self, period: &[i32], channel: usize) -> u64 {
+        let frames = period.len() / self.channels;
+        let (reference, db) = match self.target {
+            QualityTarget::Snr(db) => {
+                let energy: f64 = period
+                    .iter()
+                    .skip(channel)
+                    .step_by(self.channels)
+                    .map(|&sample| sample as f64 * sample as f64)
+                    .sum();
+                (energy, db)
+            }
+            QualityTarget::Psnr(db) => (self.full_scale_energy * frames as f64, db),
+        };
+        // errors below half a step are ignored, so near silent periods do not need the largest size
+        let floor = frames as f64 / 4.0;
+        (reference / libm::pow(10.0, db as f64 / 10.0)).max(floor) as u64
+    }
+
+    /// Smallest residual sizes meeting the target, one per channel and period
+    fn analyze(&mut self, samples: &[i32]) -> Vec<u8> {
+        let period_samples = self.scale_factor_frames * self.channels;
+        let original_lms = self.base_encoder.lms.clone();
+        let mut scale_factors = vec![0u8; self.channels];
+        let mut residuals = vec![0u8; period_samples];
+        let mut ranks = vec![0u64; self.channels];
+        let mut residual_sizes = Vec::with_capacity(samples.len().div_ceil(period_samples));
+
+        for period in samples.chunks(period_samples) {
+            let budgets: Vec<u64> = (0..self.channels)
+                .map(|channel| self.error_budget(period, channel))
+                .collect();
+            let period_lms = self.base_encoder.lms.clone();
+            let mut chosen: Vec<Option<u8>> = vec![None; self.channels];
+            let mut next_lms = period_lms.clone();
+
+            for size in 1..=MAX_RESIDUAL_SIZE {
+                self.base_encoder.lms.clone_from(&period_lms);
+                self.base_encoder.get_residuals_for_chunk_exact_sse(
+                    period,
+                    &vec![SeaResidualSize::from(size); self.channels],
+                    &mut scale_factors,
+                    &mut residuals,
+                    &mut ranks,
+                );
+                for channel in 0..self.channels {
+                    // unreachable targets get the largest size
+                    if chosen[channel].is_none()
+                        && (ranks[channel] <= budgets[channel] || size == MAX_RESIDUAL_SIZE)
+                    {
+                        chosen[channel] = Some(size);
+                        next_lms[channel] = self.base_encoder.lms[channel].clone();
+                    }
+                }
+                if chosen.iter().all(Option::is_some) {
+                    break;
+                }
+            }
+
+            self.base_encoder.lms = next_lms;
+            residual_sizes.extend(chosen.into_iter().flatten());
+        }
+
+        self.base_encoder.lms = original_lms;
+        residual_sizes
+    }
+
+    /// Encodes the periods with `residual_bits`, raising the size of periods missing the
+    /// target up to `size_limit`. Returns whether a period misses the target at the limit.
+    fn encode_periods(
+        &mut self,
+        samples: &[i32],
+        size_limit: u8,
+        residual_bits: &mut [u8],
+        scale_factors: &mut [u8],
+        residuals: &mut [u8],
+    ) -> bool {
+        let period_samples = self.scale_factor_frames * self.channels;
+        let mut ranks = vec![0u64; self.channels];
+        let mut missed_at_limit = false;
+
+        for (period, input) in samples.chunks(period_samples).enumerate() {
+            let budgets: Vec<u64> = (0..self.channels)
+                .map(|channel| self.error_budget(input, channel))
+                .collect();
+            let period_lms = self.base_encoder.lms.clone();
+            let sizes = &mut residual_bits[period * self.channels..(period + 1) * self.channels];
+            loop {
+                let residual_sizes: Vec<SeaResidualSize> = sizes
+                    .iter()
+                    .map(|&size| SeaResidualSize::from(size))
+                    .collect();
+                self.base_encoder.get_residuals_for_chunk_exact_sse(
+                    input,
+                    &residual_sizes,
+                    &mut scale_factors[period * self.channels..],
+                    &mut residuals[period * period_samples..],
+                    &mut ranks,
+                );
+
+                let mut raised = false;
+                for (channel, size) in sizes.iter_mut().enumerate() {
+                    if ranks[channel] <= budgets[channel] {
+                        continue;
+                    }
+                    if *size < size_limit {
+                        *size += 1;
+                        raised = true;
+                    } else if *size < MAX_RESIDUAL_SIZE {
+                        missed_at_limit = true;
+                    }
+                }
+                if !raised {
+                    break;
+                }
+                self.base_encoder.lms.clone_from(&period_lms);
+            }
+        }
+
+        missed_at_limit
+    }
+}
+
+impl SeaEncoderTrait for QualityEncoder {
+    fn encode(&mut self, samples: &[i32]) -> EncodedSamples {
+        let needed_sizes = self.analyze(samples);
+        let chunk_lms = self.base_encoder.lms.clone();
+
+        // sizes are stored from one below to two above the base size
+        let largest = needed_sizes.iter().copied().max().unwrap_or(1);
+        let mut residual_size = largest.saturating_sub(2).max(1);
+        loop {
+            let smallest = residual_size.saturating_sub(1).max(1);
+            let mut residual_bits: Vec<u8> = needed_sizes
+                .iter()
+                .map(|&size| size.max(smallest))
+                .collect();
+            let mut scale_factors = vec![0u8; residual_bits.len()];
+            let mut residuals = vec![0u8; samples.len()];
+
+            let missed = self.encode_periods(
+                samples,
+                (residual_size + 2).min(MAX_RESIDUAL_SIZE),
+                &mut residual_bits,
+                &mut scale_factors,
+                &mut residuals,
+            );
+            if !missed || residual_size + 2 >= MAX_RESIDUAL_SIZE {
+                return EncodedSamples {
+                    scale_factors,
+                    residuals,
+                    residual_bits,
+                    residual_size: SeaResidualSize::from(residual_size),
+                };
+            }
+
+            // a period needs more than the base size allows
+            residual_size += 1;
+            self.base_encoder.lms.clone_from(&chunk_lms);
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use alloc::{vec, vec::Vec};
 
 use crate::{
-    codec::{common::SeaResidualSize, lms::LMS_LEN},
+    codec::{common::SeaResidualSize, lms::lms_size},
     encoder::EncoderSettings,
 };
 
@@ -22,6 +22,7 @@ use super::{
 pub struct VbrEncoder {
     channels: usize,
     scale_factor_frames: u8,
+    residual_size: SeaResidualSize,
     vbr_target_bitrate: f32,
     residual_distribution: [f32; 6],
     fast_mode: bool,
@@ -48,9 +49,12 @@ impl VbrEncoder {
         VbrEncoder {
             channels: file_header.channels as usize,
             scale_factor_frames: encoder_settings.scale_factor_frames,
+            residual_size: SeaResidualSize::from(libm::floorf(encoder_settings.residual_bits) as u8),
             base_encoder: EncoderBase::new(
                 file_header.channels as usize,
                 encoder_settings.scale_factor_bits as usize,
+                file_header.sample_depth,
+                file_header.lms_order as usize,
             ),
             vbr_target_bitrate: Self::get_normalized_vbr_bitrate(
                 encoder_settings,
@@ -69,6 +73,7 @@ impl VbrEncoder {
                 ResidualBeamSearch::new(
                     file_header.channels as usize,
                     encoder_settings.scale_factor_bits as usize,
+                    file_header.sample_depth,
                     encoder_settings.vbr_residual_beam_width as usize,
                 )
             }),
@@ -79,13 +84,21 @@ impl VbrEncoder {
         &self.base_encoder.lms
     }
 
+    pub fn get_lms_mut(&mut self) -> &mut [SeaLMS] {
+        &mut self.base_encoder.lms
+    }
+
     fn get_normalized_vbr_bitrate(
         encoder_settings: &EncoderSettings,
         distribution: [f32; 6],
         correction: f32,
     ) -> f32 {
         let mut vbr_bitrate = encoder_settings.residual_bits - correction;
-        vbr_bitrate -= (LMS_LEN as f32 * 16.0 * 2.0) / encoder_settings.frames_per_chunk as f32;
+        let lms_size = lms_size(
+            encoder_settings.sample_depth,
+            encoder_settings.lms_order as usize,
+        );
+        vbr_bitrate -= (lms_size as f32 * 8.0) / encoder_settings.frames_per_chunk as f32;
         vbr_bitrate -=
             encoder_settings.scale_factor_bits as f32 / encoder_settings.scale_factor_frames as f32;
         vbr_bitrate -= 2.0 / encoder_settings.scale_factor_frames as f32;
@@ -138,9 +151,9 @@ impl VbrEncoder {
         indices.sort_unstable_by(|&a, &b| errors[a as usize].cmp(&errors[b as usize]));
 
         if self.vbr_target_bitrate >= 7.0 {
-            let high_items = ((self.vbr_target_bitrate + 0.05 - 7.0).clamp(0.0, 1.0)
-                * sortable_items as f32)
-                .round() as usize;
+            let high_items = libm::roundf(
+                (self.vbr_target_bitrate + 0.05 - 7.0).clamp(0.0, 1.0) * sortable_items as f32,
+            ) as usize;
             let mut residual_sizes = vec![7; errors.len()];
             for &index in indices.iter().rev().take(high_items) {
                 residual_sizes[index as usize] = 8;
@@ -167,7 +180,7 @@ impl VbrEncoder {
         residual_sizes
     }
 
-    fn analyze(&mut self, input: &[i16]) -> Vec<u8> {
+    fn analyze(&mut self, input: &[i32]) -> Vec<u8> {
         let analysis_width = SeaResidualSize::from(self.vbr_target_bitrate as u8 + 1);
         let period_samples = self.scale_factor_frames as usize * self.channels;
         let original_lms = self.base_encoder.lms.clone();
@@ -206,7 +219,7 @@ impl VbrEncoder {
 }
 
 impl SeaEncoderTrait for VbrEncoder {
-    fn encode(&mut self, samples: &[i16]) -> EncodedSamples {
+    fn encode(&mut self, samples: &[i32]) -> EncodedSamples {
         let period_samples = self.scale_factor_frames as usize * self.channels;
         let periods = (samples.len() / self.channels).div_ceil(self.scale_factor_frames as usize);
         let mut scale_factors = vec![0u8; periods * self.channels];
@@ -263,34 +276,8 @@ impl SeaEncoderTrait for VbrEncoder {
                     fallback_lms: &greedy_lms[channel],
                     fallback_residuals: &greedy_residuals,
                 };
-                let (error, mut next_lms, mut symbols) =
-                    beam.refine_period(&search_period, factor, width);
-                let mut chosen_factor = factor;
-                let mut chosen_error = error;
-                if let Some(second_factor) = beam.ambiguous_neighbor_factor(
-                    input,
-                    input.len() / self.channels,
-                    channel,
-                    factor,
-                    width,
-                    &lms[channel],
-                ) {
-                    let (second_error, second_lms, second_symbols) =
-                        beam.refine_period(&search_period, second_factor, width);
-                    if second_error < error {
-                        next_lms = second_lms;
-                        symbols = second_symbols;
-                        chosen_factor = second_factor;
-                        chosen_error = second_error;
-                    }
-                }
-                if chosen_error >= ranks[channel] {
-                    next_lms = greedy_lms[channel].clone();
-                    symbols = core::array::from_fn(|frame| {
-                        greedy_residuals[frame * self.channels + channel]
-                    });
-                    chosen_factor = factor;
-                }
+                let (chosen_factor, next_lms, symbols) =
+                    beam.refine_channel(&search_period, factor, width, ranks[channel]);
                 scale_factors[period * self.channels + channel] = chosen_factor as u8;
                 lms[channel] = next_lms;
                 for frame in 0..(input.len() / self.channels) {
@@ -305,6 +292,7 @@ impl SeaEncoderTrait for VbrEncoder {
             scale_factors,
             residuals,
             residual_bits,
+            residual_size: self.residual_size,
         }
     }
 }
@@ -312,17 +300,18 @@ impl SeaEncoderTrait for VbrEncoder {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::codec::common::clamp_i16;
+    use crate::codec::common::{clamp_sample, sample_max};
     use crate::codec::dqt::SeaDequantTab;
 
     fn exhaustive_nearest_two(
         levels: &[i32],
         prediction: i32,
-        sample: i16,
-    ) -> [Option<(u64, usize, i16, i32)>; 2] {
-        let mut nearest: [Option<(u64, usize, i16, i32)>; 2] = [None, None];
+        sample: i32,
+        sample_max: i32,
+    ) -> [Option<(u64, usize, i32, i32)>; 2] {
+        let mut nearest: [Option<(u64, usize, i32, i32)>; 2] = [None, None];
         for (symbol, &decoded) in levels.iter().enumerate() {
-            let reconstructed = clamp_i16(prediction + decoded);
+            let reconstructed = clamp_sample(prediction + decoded, sample_max);
             let difference = sample as i64 - reconstructed as i64;
             let candidate = (
                 (difference * difference) as u64,
@@ -348,16 +337,23 @@ mod tests {
 
     #[test]
     fn fast_nearest_symbols_match_exhaustive_search() {
-        let dequant = SeaDequantTab::init(4);
-        for width in 1..=8 {
-            for levels in dequant.get_dqt(width) {
-                for prediction in [-40_000, -32_768, -20_000, 0, 20_000, 32_767, 40_000] {
-                    for sample in [-32_768, -20_000, -1, 0, 1, 20_000, 32_767] {
-                        assert_eq!(
-                            ResidualBeamSearch::nearest_two_symbols(levels, prediction, sample),
-                            exhaustive_nearest_two(levels, prediction, sample),
-                            "width={width}, prediction={prediction}, sample={sample}",
-                        );
+        for sample_depth in [16, 24] {
+            let dequant = SeaDequantTab::init(4, sample_depth);
+            let sample_max = sample_max(sample_depth);
+            let scale = 1 << (sample_depth - 16);
+            for width in 1..=8 {
+                for levels in dequant.get_dqt(width) {
+                    for prediction in [-40_000, -32_768, -20_000, 0, 20_000, 32_767, 40_000] {
+                        for sample in [-32_768, -20_000, -1, 0, 1, 20_000, 32_767] {
+                            let (prediction, sample) = (prediction * scale, sample * scale);
+                            assert_eq!(
+                                ResidualBeamSearch::nearest_two_symbols(
+                                    levels, prediction, sample, sample_max
+                                ),
+                                exhaustive_nearest_two(levels, prediction, sample, sample_max),
+                                "depth={sample_depth}, width={width}, prediction={prediction}, sample={sample}",
+                            );
+                        }
                     }
                 }
             }
@@ -1,7 +1,11 @@
-use super::{common::clamp_i16, dqt::SeaDequantTab, lms::SeaLMS};
+use super::{
+    common::{clamp_sample, sample_max},
+    dqt::SeaDequantTab,
+    lms::SeaLMS,
+};
 
 const MAX_RESIDUAL_BEAM_WIDTH: usize = 6;
-const MAX_PERIOD_FRAMES: usize = 20;
+pub(crate) const MAX_PERIOD_FRAMES: usize = 20;
 const ADAPTIVE_SECOND_FACTOR_GAP_PERCENT: u64 = 25;
 
 #[derive(Clone)]
@@ -19,7 +23,7 @@ struct TraceNode {
 }
 
 pub(super) struct BeamPeriod<'a> {
-    pub(super) input: &'a [i16],
+    pub(super) input: &'a [i32],
     pub(super) start: usize,
     pub(super) frames: usize,
     pub(super) channel: usize,
@@ -28,19 +32,26 @@ pub(super) struct BeamPeriod<'a> {
     pub(super) fallback_residuals: &'a [u8],
 }
 
-/// Bounded residual-path refinement used by non-fast VBR effort levels.
+/// Bounded residual-path refinement used by the non-fast CBR and VBR effort levels.
 pub(super) struct ResidualBeamSearch {
     channels: usize,
     residual_beam_width: usize,
+    sample_max: i32,
     dequant: SeaDequantTab,
 }
 
 impl ResidualBeamSearch {
-    pub(super) fn new(channels: usize, scale_factor_bits: usize, beam_width: usize) -> Self {
+    pub(super) fn new(
+        channels: usize,
+        scale_factor_bits: usize,
+        sample_depth: u8,
+        beam_width: usize,
+    ) -> Self {
         Self {
             channels,
             residual_beam_width: beam_width.clamp(1, MAX_RESIDUAL_BEAM_WIDTH),
-            dequant: SeaDequantTab::init(scale_factor_bits),
+            sample_max: sample_max(sample_depth),
+            dequant: SeaDequantTab::init(scale_factor_bits, sample_depth),
         }
     }
     pub(super) fn refine_period(
@@ -75,7 +86,8 @@ impl ResidualBeamSearch {
                     continue;
                 };
                 debug_assert_eq!(prediction, path.lms.predict());
-                let nearest = Self::nearest_two_symbols(&levels[factor], prediction, sample);
+                let nearest =
+                    Self::nearest_two_symbols(&levels[factor], prediction, sample, self.sample_max);
                 for (error, symbol, reconstructed, decoded) in nearest.into_iter().flatten() {
                     let mut next = path.clone();
                     next.error = next.error.saturating_add(error);
@@ -140,9 +152,49 @@ impl ResidualBeamSearch {
         (u64::MAX, period.fallback_lms.clone(), residuals)
     }
 
+    /// Refines the greedy encoding of one channel in a period, also trying a neighbouring
+    /// scale factor when it comes close. Returns the scale factor, the predictor after the
+    /// period and the residuals, the greedy result unless the search has a smaller error.
+    pub(super) fn refine_channel(
+        &self,
+        period: &BeamPeriod,
+        factor: usize,
+        width: usize,
+        greedy_error: u64,
+    ) -> (usize, SeaLMS, [u8; MAX_PERIOD_FRAMES]) {
+        let (error, mut lms, mut symbols) = self.refine_period(period, factor, width);
+        let mut chosen_factor = factor;
+        let mut chosen_error = error;
+        if let Some(second_factor) = self.ambiguous_neighbor_factor(
+            period.input,
+            period.frames,
+            period.channel,
+            factor,
+            width,
+            period.initial_lms,
+        ) {
+            let (second_error, second_lms, second_symbols) =
+                self.refine_period(period, second_factor, width);
+            if second_error < error {
+                lms = second_lms;
+                symbols = second_symbols;
+                chosen_factor = second_factor;
+                chosen_error = second_error;
+            }
+        }
+        if chosen_error >= greedy_error {
+            lms = period.fallback_lms.clone();
+            symbols = core::array::from_fn(|frame| {
+                period.fallback_residuals[frame * self.channels + period.channel]
+            });
+            chosen_factor = factor;
+        }
+        (chosen_factor, lms, symbols)
+    }
+
     fn factor_sse(
         &self,
-        input: &[i16],
+        input: &[i32],
         frames: usize,
         channel: usize,
         factor: usize,
@@ -156,7 +208,8 @@ impl ResidualBeamSearch {
             let prediction = Self::safe_predict(&lms)?;
             let sample = input[frame * self.channels + channel];
             let nearest =
-                Self::nearest_two_symbols(&levels[factor], prediction, sample)[0].unwrap();
+                Self::nearest_two_symbols(&levels[factor], prediction, sample, self.sample_max)[0]
+                    .unwrap();
             error = error.saturating_add(nearest.0);
             if !Self::safe_update(&mut lms, nearest.2, nearest.3) {
                 return None;
@@ -168,7 +221,7 @@ impl ResidualBeamSearch {
 
     pub(super) fn ambiguous_neighbor_factor(
         &self,
-        input: &[i16],
+        input: &[i32],
         frames: usize,
         channel: usize,
         factor: usize,
@@ -205,20 +258,15 @@ impl ResidualBeamSearch {
     /// the second-nearest symbol as well.
     #[inline(always)]
     fn safe_predict(lms: &SeaLMS) -> Option<i32> {
-        // Beam updates keep both arrays in the signed 16-bit domain, so four
-        // products cannot overflow this i64 accumulator.
-        let prediction = lms.weights[0] as i64 * lms.history[0] as i64
-            + lms.weights[1] as i64 * lms.history[1] as i64
-            + lms.weights[2] as i64 * lms.history[2] as i64
-            + lms.weights[3] as i64 * lms.history[3] as i64;
-        i32::try_from(prediction).ok().map(|value| value >> 13)
+        lms.checked_predict()
     }
 
     #[inline(always)]
-    fn safe_update(lms: &mut SeaLMS, sample: i16, residual: i32) -> bool {
-        let delta = residual >> 4;
+    fn safe_update(lms: &mut SeaLMS, sample: i32, residual: i32) -> bool {
+        let order = lms.order();
+        let delta = lms.update_delta(residual);
         let mut weights = lms.weights;
-        for (weight, &history) in weights.iter_mut().zip(&lms.history) {
+        for (weight, &history) in weights[..order].iter_mut().zip(&lms.history[..order]) {
             let adjustment = if history < 0 { -delta } else { delta };
             let next = *weight + adjustment;
             if !(i16::MIN as i32..=i16::MAX as i32).contains(&next) {
@@ -227,8 +275,8 @@ impl ResidualBeamSearch {
             *weight = next;
         }
         lms.weights = weights;
-        lms.history.copy_within(1.., 0);
-        lms.history[3] = sample as i32;
+        lms.history.copy_within(1..order, 0);
+        lms.history[order - 1] = sample;
         true
     }
 
@@ -239,12 +287,13 @@ impl ResidualBeamSearch {
     pub(super) fn nearest_two_symbols(
         levels: &[i32],
         prediction: i32,
-        sample: i16,
-    ) -> [Option<(u64, usize, i16, i32)>; 2] {
+        sample: i32,
+        sample_max: i32,
+    ) -> [Option<(u64, usize, i32, i32)>; 2] {
         let max_magnitude = levels[levels.len() - 2];
-        let unclipped = prediction - max_magnitude >= i16::MIN as i32
-            && prediction + max_magnitude <= i16::MAX as i32;
-        let target = (sample as i32 - prediction).unsigned_abs() as i32;
+        let unclipped = prediction - max_magnitude >= -sample_max - 1
+            && prediction + max_magnitude <= sample_max;
+        let target = (sample - prediction).unsigned_abs() as i32;
         let pair_count = levels.len() / 2;
         let mut first = 0usize;
         let mut last = pair_count;
@@ -263,11 +312,11 @@ impl ResidualBeamSearch {
         } else {
             (0, pair_count)
         };
-        let mut nearest: [Option<(u64, usize, i16, i32)>; 2] = [None, None];
+        let mut nearest: [Option<(u64, usize, i32, i32)>; 2] = [None, None];
         for pair in start..end {
             for symbol in [pair * 2, pair * 2 + 1] {
                 let decoded = levels[symbol];
-                let reconstructed = clamp_i16(prediction + decoded);
+                let reconstructed = clamp_sample(prediction + decoded, sample_max);
                 let difference = sample as i64 - reconstructed as i64;
                 let candidate = (
                     (difference * difference) as u64,
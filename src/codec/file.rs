@@ -1,76 +1,223 @@
-use alloc::{rc::Rc, string::String, vec::Vec};
+use alloc::{rc::Rc, string::String, vec, vec::Vec};
 
 use crate::{
-    codec::{chunk::SeaChunk, common::read_max_or_zero},
+    codec::{
+        chunk::{SeaChunk, SeaChunkView},
+        common::read_max,
+    },
     cursor::Cursor,
     encoder::EncoderSettings,
+    metadata::{SeaMetadata, MAX_METADATA_SIZE},
 };
 
 use super::{
-    chunk::SeaChunkType,
-    common::{
-        read_u16_le, read_u32_be, read_u32_le, read_u8, SeaEncoderTrait, SeaError, SEAC_MAGIC,
-    },
-    decoder::Decoder,
+    common::{sample_max, EncodedSamples, SeaEncoderTrait, SeaError, SEAC_MAGIC},
+    decoder::{Decoder, SampleWriter},
     encoder_cbr::CbrEncoder,
+    encoder_quality::QualityEncoder,
     encoder_vbr::VbrEncoder,
+    format::{
+        lms_order_flags, lms_order_from_flags, sample_depth_flags, sample_depth_from_flags,
+        SeaFormat, FLAG_CHUNK_CRC, FLAG_SYNC, FLAG_VARIABLE_CHUNKS, KNOWN_FLAGS, MAX_VERSION,
+        WIDE_HEADER_VERSION,
+    },
+    lms::SeaLMS,
+    stereo::{convert_lms_history, prefers_mid_side, samples_to_mid_side},
+    sync::SeaSyncHeader,
 };
 
 #[derive(Debug, Clone)]
 pub struct SeaFileHeader {
     pub version: u8,
     pub channels: u8,
-    pub chunk_size: u16,
+    /// Stored with 32 bits from version 3, with 16 bits before
+    pub chunk_size: u32,
     pub frames_per_chunk: u16,
     pub sample_rate: u32,
-    pub total_frames: u32,
-    pub metadata: Rc<String>,
+    /// Stored with 64 bits from version 3, with 32 bits before
+    pub total_frames: u64,
+    /// Every chunk carries a CRC-32 of its contents (version 2+)
+    pub chunk_crc: bool,
+    /// Every chunk is preceded by a sync header (version 2+)
+    pub sync: bool,
+    /// Bits per sample, 16, 20 or 24. Depths above 16 require version 2.
+    pub sample_depth: u8,
+    /// Chunks vary in size up to `chunk_size` (version 2+)
+    pub variable_chunks: bool,
+    /// Taps of the LMS predictors, 2, 4, 8 or 16. Orders other than 4 require version 2.
+    pub lms_order: u8,
+    pub metadata: Rc<SeaMetadata>,
 }
 
 impl SeaFileHeader {
-    fn validate(&self) -> bool {
-        self.channels > 0
-            && self.chunk_size >= 16
-            && self.frames_per_chunk > 0
-            && self.sample_rate > 0
-    }
-
-    pub fn from_reader(reader: &mut Cursor) -> Result<Self, SeaError> {
-        let magic = read_u32_be(reader)?;
-        if magic != SEAC_MAGIC {
-            return Err(SeaError::InvalidFile);
-        }
-        let version = read_u8(reader)?;
-        let channels = read_u8(reader)?;
-        let chunk_size = read_u16_le(reader)?;
-        let frames_per_chunk = read_u16_le(reader)?;
-        let sample_rate = read_u32_le(reader)?;
-        let total_frames = read_u32_le(reader)?;
-        let metadata_size = read_u32_le(reader)?;
-
-        let mut metadata = Vec::<u8>::with_capacity(metadata_size as usize);
-        reader.read_exact(&mut metadata)?;
-        let metadata_string = String::from_utf8(metadata).unwrap();
-
-        let res: SeaFileHeader = Self {
-            version,
+    pub(crate) fn new(
+        channels: u8,
+        sample_rate: u32,
+        total_frames: u64,
+        settings: &EncoderSettings,
+    ) -> Self {
+        let mut header = SeaFileHeader {
+            version: 1,
             channels,
-            chunk_size,
-            frames_per_chunk,
+            chunk_size: 0, // will be set later by the first chunk
+            frames_per_chunk: settings.frames_per_chunk,
             sample_rate,
             total_frames,
-            metadata: Rc::new(metadata_string),
+            chunk_crc: settings.checksum,
+            sync: settings.sync,
+            sample_depth: settings.sample_depth,
+            variable_chunks: settings.quality_target.is_some(),
+            lms_order: settings.lms_order,
+            metadata: Rc::new(SeaMetadata::new()),
+        };
+        header.version = header.required_version();
+        // chunk flags are only stored from version 2
+        if settings.mid_side && channels >= 2 {
+            header.version = header.version.max(2);
+        }
+        if settings.long_frame_count {
+            header.version = WIDE_HEADER_VERSION;
+        }
+        header
+    }
+
+    /// Lowest file version able to represent this header, version 1 files stay
+    /// readable by older decoders.
+    fn required_version(&self) -> u8 {
+        if self.total_frames > u32::MAX as u64 {
+            WIDE_HEADER_VERSION
+        } else if self.flags() != 0 {
+            2
+        } else {
+            1
+        }
+    }
+
+    pub(crate) fn flags(&self) -> u8 {
+        let mut flags = 0;
+        if self.chunk_crc {
+            flags |= FLAG_CHUNK_CRC;
+        }
+        if self.sync {
+            flags |= FLAG_SYNC;
+        }
+        if self.variable_chunks {
+            flags |= FLAG_VARIABLE_CHUNKS;
+        }
+        // the sample depth and LMS order are validated by `EncoderSettings::validate`
+        flags |= sample_depth_flags(self.sample_depth).unwrap_or(0);
+        flags |= lms_order_flags(self.lms_order).unwrap_or(0);
+        flags
+    }
+
+    pub(crate) fn format(&self) -> SeaFormat {
+        SeaFormat {
+            version: self.version,
+            channels: self.channels,
+            chunk_size: self.chunk_size,
+            frames_per_chunk: self.frames_per_chunk,
+            sample_rate: self.sample_rate,
+            total_frames: self.total_frames,
+            flags: self.flags(),
+        }
+    }
+
+    /// Size of the fixed chunk header, including the optional checksum
+    pub fn chunk_header_size(&self) -> usize {
+        self.format().chunk_header_size()
+    }
+
+    /// Size of the sync header in front of each chunk, zero if sync framing is disabled
+    pub fn sync_header_size(&self) -> usize {
+        self.format().sync_header_size()
+    }
+
+    /// Distance between the starts of two consecutive chunks in the file
+    pub fn chunk_stride(&self) -> usize {
+        self.format().chunk_stride()
+    }
+
+    /// Rebuilds the header of a sync framed stream, metadata is only stored in the file header.
+    pub(crate) fn from_sync_header(sync_header: &SeaSyncHeader) -> Result<Self, SeaError> {
+        if !(2..=MAX_VERSION).contains(&sync_header.version) {
+            return Err(SeaError::UnsupportedVersion {
+                version: sync_header.version,
+            });
+        }
+        let unsupported_flags = SeaError::UnsupportedFlags {
+            flags: sync_header.flags,
+        };
+        if sync_header.flags & !KNOWN_FLAGS != 0 || sync_header.flags & FLAG_SYNC == 0 {
+            return Err(unsupported_flags);
+        }
+        let sample_depth = sample_depth_from_flags(sync_header.flags).ok_or(unsupported_flags)?;
+
+        let res = Self {
+            version: sync_header.version,
+            channels: sync_header.channels,
+            chunk_size: sync_header.chunk_size as u32,
+            frames_per_chunk: sync_header.frames_per_chunk,
+            sample_rate: sync_header.sample_rate,
+            total_frames: 0,
+            chunk_crc: sync_header.flags & FLAG_CHUNK_CRC != 0,
+            sync: true,
+            sample_depth,
+            variable_chunks: sync_header.flags & FLAG_VARIABLE_CHUNKS != 0,
+            lms_order: lms_order_from_flags(sync_header.flags),
+            metadata: Rc::new(SeaMetadata::new()),
         };
 
-        if !res.validate() {
-            return Err(SeaError::InvalidFile);
+        if !res.format().is_valid() {
+            return Err(SeaError::InvalidFile {
+                reason: "invalid stream parameters",
+            });
         }
 
         Ok(res)
     }
 
-    pub fn set_total_frames(&mut self, total_frames: u32) {
+    pub fn from_reader(reader: &mut Cursor) -> Result<Self, SeaError> {
+        let (format, metadata_size) = SeaFormat::from_reader(reader)?;
+
+        // checked before reading, so a corrupt size cannot make a stream buffer gigabytes
+        if metadata_size as usize > MAX_METADATA_SIZE {
+            return Err(SeaError::MetadataTooLarge);
+        }
+        let mut metadata = vec![0u8; metadata_size as usize];
+        reader.read_exact(&mut metadata)?;
+        // invalid characters in a tag do not make the audio undecodable
+        let metadata_string = String::from_utf8_lossy(&metadata);
+
+        if !format.is_valid() {
+            return Err(SeaError::InvalidFile {
+                reason: "invalid stream parameters",
+            });
+        }
+
+        Ok(Self {
+            version: format.version,
+            channels: format.channels,
+            chunk_size: format.chunk_size,
+            frames_per_chunk: format.frames_per_chunk,
+            sample_rate: format.sample_rate,
+            total_frames: format.total_frames,
+            chunk_crc: format.chunk_crc(),
+            sync: format.sync(),
+            sample_depth: format.sample_depth(),
+            variable_chunks: format.variable_chunks(),
+            lms_order: format.lms_order() as u8,
+            metadata: Rc::new(SeaMetadata::parse(&metadata_string)),
+        })
+    }
+
+    /// Returns `TooManyFrames` if the count needs 64 bits, but the header was written
+    /// with an older version.
+    pub fn set_total_frames(&mut self, total_frames: u64) -> Result<(), SeaError> {
+        if self.version < WIDE_HEADER_VERSION && total_frames > u32::MAX as u64 {
+            return Err(SeaError::TooManyFrames);
+        }
         self.total_frames = total_frames;
+        Ok(())
     }
 
     pub fn serialize(&self) -> Vec<u8> {
@@ -79,25 +226,49 @@ impl SeaFileHeader {
         output.extend_from_slice(&SEAC_MAGIC.to_be_bytes());
         output.extend_from_slice(&self.version.to_le_bytes());
         output.extend_from_slice(&self.channels.to_le_bytes());
-        output.extend_from_slice(&self.chunk_size.to_le_bytes());
+        if self.version >= WIDE_HEADER_VERSION {
+            output.extend_from_slice(&self.chunk_size.to_le_bytes());
+        } else {
+            output.extend_from_slice(&(self.chunk_size as u16).to_le_bytes());
+        }
         output.extend_from_slice(&self.frames_per_chunk.to_le_bytes());
         output.extend_from_slice(&self.sample_rate.to_le_bytes());
-        output.extend_from_slice(&self.total_frames.to_le_bytes());
-        let metadata_len_u32 = self.metadata.len() as u32;
+        if self.version >= WIDE_HEADER_VERSION {
+            output.extend_from_slice(&self.total_frames.to_le_bytes());
+        } else {
+            output.extend_from_slice(&(self.total_frames as u32).to_le_bytes());
+        }
+        if self.version >= 2 {
+            output.push(self.flags());
+        }
+        let metadata = self.metadata.serialize();
+        let metadata_len_u32 = metadata.len() as u32;
         output.extend_from_slice(&metadata_len_u32.to_le_bytes());
-        output.extend_from_slice(self.metadata.as_bytes());
+        output.extend_from_slice(metadata.as_bytes());
 
         output
     }
 }
 
-#[expect(
-    clippy::large_enum_variant,
-    reason = "keeping encoders inline avoids heap allocation and per-chunk pointer indirection"
-)]
+/// Size of a chunk with 8 bit residuals, the upper bound of variable chunk sizes
+fn max_chunk_size(header: &SeaFileHeader, encoder_settings: &EncoderSettings) -> usize {
+    let format = header.format();
+    let channels = header.channels as usize;
+    let frames = header.frames_per_chunk as usize;
+    let scale_factor_items =
+        frames.div_ceil(encoder_settings.scale_factor_frames as usize) * channels;
+
+    format.chunk_header_size()
+        + channels * format.lms_size()
+        + (scale_factor_items * encoder_settings.scale_factor_bits as usize).div_ceil(8)
+        + (scale_factor_items * 2).div_ceil(8)
+        + frames * channels
+}
+
 enum ActiveEncoder {
     Cbr(CbrEncoder),
     Vbr(VbrEncoder),
+    Quality(QualityEncoder),
 }
 
 pub struct SeaFile {
@@ -107,6 +278,13 @@ pub struct SeaFile {
 
     encoder: Option<ActiveEncoder>,
     encoder_settings: Option<EncoderSettings>,
+
+    // sync header found while joining a stream, belongs to the next chunk
+    pending_sync_header: Option<SeaSyncHeader>,
+    // encoded chunk, reused between chunks
+    chunk_buffer: Vec<u8>,
+    // the previous chunk stored mid and side, the encoder predictors follow it
+    mid_side: bool,
 }
 
 impl SeaFile {
@@ -114,7 +292,10 @@ impl SeaFile {
         header: SeaFileHeader,
         encoder_settings: &EncoderSettings,
     ) -> Result<Self, SeaError> {
-        let encoder = if encoder_settings.vbr {
+        let encoder = if let Some(target) = encoder_settings.quality_target {
+            let quality_encoder = QualityEncoder::new(&header, encoder_settings, target);
+            Some(ActiveEncoder::Quality(quality_encoder))
+        } else if encoder_settings.vbr {
             let vbr_encoder = VbrEncoder::new(&header, &encoder_settings.clone());
             Some(ActiveEncoder::Vbr(vbr_encoder))
         } else {
@@ -127,6 +308,9 @@ impl SeaFile {
             decoder: None,
             encoder,
             encoder_settings: Some(encoder_settings.clone()),
+            pending_sync_header: None,
+            chunk_buffer: Vec::new(),
+            mid_side: false,
         })
     }
 
@@ -138,76 +322,242 @@ impl SeaFile {
             decoder: None,
             encoder: None,
             encoder_settings: None,
+            pending_sync_header: None,
+            chunk_buffer: Vec::new(),
+            mid_side: false,
         })
     }
 
-    pub fn make_chunk(&mut self, samples: &[i16]) -> Result<Vec<u8>, SeaError> {
+    /// Starts decoding a sync framed stream at the first sync header found in the input.
+    pub fn from_sync_reader(reader: &mut Cursor) -> Result<Self, SeaError> {
+        let sync_header = SeaSyncHeader::find(reader, |header| {
+            SeaFileHeader::from_sync_header(header).is_ok()
+        })?
+        .ok_or(SeaError::InvalidFile {
+            reason: "no sync header found",
+        })?;
+
+        Ok(SeaFile {
+            header: SeaFileHeader::from_sync_header(&sync_header)?,
+            decoder: None,
+            encoder: None,
+            encoder_settings: None,
+            pending_sync_header: Some(sync_header),
+            chunk_buffer: Vec::new(),
+            mid_side: false,
+        })
+    }
+
+    /// Encodes `samples` with the active encoder and returns the chunk mode, the predictors
+    /// before the samples and the encoded samples
+    fn encode_samples(&mut self, samples: &[i32]) -> (bool, Vec<SeaLMS>, EncodedSamples) {
         let encoder_settings = self.encoder_settings.as_ref().unwrap();
         let encoder = self.encoder.as_mut().unwrap();
 
+        let channels = self.header.channels as usize;
+        let sample_max = sample_max(self.header.sample_depth);
+        let mid_side = encoder_settings.mid_side
+            && channels >= 2
+            && prefers_mid_side(samples, channels, sample_max);
+        if mid_side != self.mid_side {
+            let lms = match encoder {
+                ActiveEncoder::Cbr(encoder) => encoder.get_lms_mut(),
+                ActiveEncoder::Vbr(encoder) => encoder.get_lms_mut(),
+                ActiveEncoder::Quality(encoder) => encoder.get_lms_mut(),
+            };
+            convert_lms_history(lms, mid_side, sample_max);
+            self.mid_side = mid_side;
+        }
+        let mid_side_samples;
+        let samples = if mid_side {
+            let mut converted = samples.to_vec();
+            samples_to_mid_side(&mut converted, channels);
+            mid_side_samples = converted;
+            &mid_side_samples
+        } else {
+            samples
+        };
+
         let initial_lms = match encoder {
             ActiveEncoder::Cbr(encoder) => encoder.get_lms().clone(),
             ActiveEncoder::Vbr(encoder) => encoder.get_lms().clone(),
+            ActiveEncoder::Quality(encoder) => encoder.get_lms().clone(),
         };
 
         let encoded = match encoder {
             ActiveEncoder::Cbr(encoder) => encoder.encode(samples),
             ActiveEncoder::Vbr(encoder) => encoder.encode(samples),
+            ActiveEncoder::Quality(encoder) => encoder.encode(samples),
         };
 
-        let chunk = SeaChunk::new(
+        (mid_side, initial_lms, encoded)
+    }
+
+    /// Runs the encoder over `samples` without writing a chunk, so the predictors adapt to
+    /// the signal preceding the next chunk. Used when encoding does not start at the beginning
+    /// of the input.
+    #[cfg(feature = "parallel")]
+    pub fn warm_up(&mut self, samples: &[i32]) {
+        self.encode_samples(samples);
+    }
+
+    /// Encodes a chunk of samples, which have to be within the range of the sample depth
+    pub fn make_chunk(&mut self, samples: &[i32]) -> Result<Vec<u8>, SeaError> {
+        let (mid_side, initial_lms, encoded) = self.encode_samples(samples);
+        let encoder_settings = self.encoder_settings.as_ref().unwrap();
+
+        let mut chunk = SeaChunk::new(
             &self.header,
             &initial_lms,
             encoder_settings,
+            encoded.residual_size,
             encoded.scale_factors,
             encoded.residual_bits,
             encoded.residuals,
         );
-        let output = chunk.serialize();
+        chunk.mid_side = mid_side;
+        let mut output = chunk.serialize();
 
+        // the header is written after the first chunk, so it can still switch to a wider version
         if self.header.chunk_size == 0 {
-            self.header.chunk_size = output.len() as u16;
+            let chunk_size = if self.header.variable_chunks {
+                max_chunk_size(&self.header, encoder_settings)
+            } else {
+                output.len()
+            };
+            self.set_chunk_size(chunk_size)?;
+            if chunk.version != self.header.version {
+                // the chunk header depends on the version, its size does not
+                chunk.version = self.header.version;
+                output = chunk.serialize();
+            }
+        }
+
+        if output.len() > self.header.chunk_size as usize {
+            return Err(SeaError::InvalidParameters);
         }
 
         let full_samples_len =
             self.header.frames_per_chunk as usize * self.header.channels as usize;
 
-        if samples.len() == full_samples_len {
-            assert_eq!(self.header.chunk_size, output.len() as u16);
+        if samples.len() == full_samples_len && !self.header.variable_chunks {
+            assert_eq!(self.header.chunk_size as usize, output.len());
+        }
+
+        if self.header.sync {
+            let frames = (samples.len() / self.header.channels as usize) as u16;
+            let sync_header = SeaSyncHeader::new(&self.header, frames).serialize();
+            output.splice(0..0, sync_header);
         }
 
         Ok(output)
     }
 
-    pub fn samples_from_reader(
+    /// Sets the chunk size of the header, switching to the version that can store it
+    fn set_chunk_size(&mut self, chunk_size: usize) -> Result<(), SeaError> {
+        let chunk_size = u32::try_from(chunk_size).map_err(|_| SeaError::InvalidParameters)?;
+        if chunk_size > u16::MAX as u32 {
+            // sync headers store the chunk size with 16 bits
+            if self.header.sync {
+                return Err(SeaError::InvalidParameters);
+            }
+            self.header.version = self.header.version.max(WIDE_HEADER_VERSION);
+        }
+        self.header.chunk_size = chunk_size;
+        Ok(())
+    }
+
+    /// Serialized header of a file without chunks. Decoders reject a zero chunk size, so it
+    /// gets the largest size a chunk with these settings can have.
+    pub fn empty_header(&mut self) -> Result<Vec<u8>, SeaError> {
+        if self.header.chunk_size == 0 {
+            let encoder_settings = self.encoder_settings.as_ref().unwrap();
+            self.set_chunk_size(max_chunk_size(&self.header, encoder_settings))?;
+        }
+        Ok(self.header.serialize())
+    }
+
+    /// Forgets the sync header read while joining the stream, the next chunk is read from
+    /// a new position after a seek and starts with its own sync header
+    pub fn discard_pending_sync_header(&mut self) {
+        self.pending_sync_header = None;
+    }
+
+    /// Reads a chunk of a variable chunk size file into the chunk buffer, section by section,
+    /// so the next chunk is not consumed. Returns its size, zero at the end of the input.
+    fn read_variable_chunk(
         &mut self,
         reader: &mut Cursor,
+        format: &SeaFormat,
         remaining_frames: Option<usize>,
-        output: &mut Vec<i16>,
     ) -> Result<usize, SeaError> {
-        let encoded = read_max_or_zero(reader, self.header.chunk_size as usize)?;
-        if encoded.is_empty() {
-            return Ok(0);
-        }
+        let mut bytes_read = 0;
+        loop {
+            let chunk_size = SeaChunkView::encoded_size(
+                &self.chunk_buffer[..bytes_read],
+                format,
+                remaining_frames,
+            )?;
+            if chunk_size <= bytes_read {
+                return Ok(chunk_size);
+            }
 
-        let chunk = SeaChunk::from_slice(&encoded, &self.header, remaining_frames);
-
-        match chunk {
-            Ok(chunk) => {
-                if self.decoder.is_none() {
-                    self.decoder = Some(Decoder::init(
-                        self.header.channels as usize,
-                        chunk.scale_factor_bits as usize,
-                    ));
-                }
-                let decoder = self.decoder.as_mut().unwrap();
-                match chunk.chunk_type {
-                    SeaChunkType::Cbr => decoder.decode_cbr(&chunk, output),
-                    SeaChunkType::Vbr => decoder.decode_vbr(&chunk, output),
-                };
-                Ok(self.header.channels as usize * chunk.frames_per_chunk)
+            let section = self
+                .chunk_buffer
+                .get_mut(bytes_read..chunk_size)
+                .ok_or(SeaError::INVALID_FRAME)?;
+            let section_read = read_max(reader, section)?;
+            if bytes_read == 0 && section_read == 0 {
+                return Ok(0);
+            }
+            if section_read < section.len() {
+                return Err(SeaError::INVALID_FRAME);
             }
-            Err(err) => Err(err),
+            bytes_read = chunk_size;
+        }
+    }
+
+    /// Reads and decodes the next chunk into `output`, which has to have room for a full chunk.
+    /// Returns the number of samples written, zero at the end of the input.
+    pub fn decode_chunk(
+        &mut self,
+        reader: &mut Cursor,
+        remaining_frames: Option<usize>,
+        output: &mut impl SampleWriter,
+    ) -> Result<usize, SeaError> {
+        let format = self.header.format();
+        let mut remaining_frames = remaining_frames;
+        if format.sync() {
+            let sync_header = match self.pending_sync_header.take() {
+                Some(sync_header) => sync_header,
+                // skips over damaged or missing data until the next valid chunk
+                None => match SeaSyncHeader::find(reader, |header| header.matches(&format))? {
+                    Some(sync_header) => sync_header,
+                    None => return Ok(0),
+                },
+            };
+            let frames = sync_header.frames as usize;
+            remaining_frames =
+                Some(remaining_frames.map_or(frames, |remaining| remaining.min(frames)));
         }
+
+        // the buffer is only allocated for the first chunk
+        self.chunk_buffer.resize(self.header.chunk_size as usize, 0);
+        let bytes_read = if format.variable_chunks() {
+            self.read_variable_chunk(reader, &format, remaining_frames)?
+        } else {
+            read_max(reader, &mut self.chunk_buffer)?
+        };
+        if bytes_read == 0 {
+            return Ok(0);
+        }
+
+        let chunk =
+            SeaChunkView::from_slice(&self.chunk_buffer[..bytes_read], &format, remaining_frames)?;
+
+        let decoder = self
+            .decoder
+            .get_or_insert_with(|| Decoder::init(self.header.channels as usize));
+        Ok(decoder.decode(&chunk, output))
     }
 }
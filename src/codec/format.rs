@@ -0,0 +1,188 @@
+use crate::cursor::Cursor;
+
+use super::{
+    common::{read_u16_le, read_u32_be, read_u32_le, read_u64_le, read_u8, SeaError, SEAC_MAGIC},
+    lms::{lms_size, DEFAULT_LMS_LEN},
+    sync::SYNC_HEADER_SIZE,
+};
+
+/// Newest file version understood by the decoder
+pub const MAX_VERSION: u8 = 3;
+/// First version storing the chunk size with 32 bits and the total frame count with 64 bits
+pub const WIDE_HEADER_VERSION: u8 = 3;
+
+// header flags, introduced in version 2
+pub const FLAG_CHUNK_CRC: u8 = 0x01;
+pub const FLAG_SYNC: u8 = 0x02;
+// two bits selecting an entry of `SAMPLE_DEPTHS`, the last code is reserved
+pub const FLAGS_SAMPLE_DEPTH: u8 = 0x0c;
+const FLAGS_SAMPLE_DEPTH_SHIFT: u8 = 2;
+// chunks are at most `chunk_size` bytes long and follow each other without padding
+pub const FLAG_VARIABLE_CHUNKS: u8 = 0x10;
+// two bits selecting an entry of `LMS_ORDERS`
+pub const FLAGS_LMS_ORDER: u8 = 0x60;
+const FLAGS_LMS_ORDER_SHIFT: u8 = 5;
+pub const KNOWN_FLAGS: u8 =
+    FLAG_CHUNK_CRC | FLAG_SYNC | FLAGS_SAMPLE_DEPTH | FLAG_VARIABLE_CHUNKS | FLAGS_LMS_ORDER;
+
+/// Sample depths that can be stored in a file
+pub const SAMPLE_DEPTHS: [u8; 3] = [16, 20, 24];
+
+/// LMS predictor orders that can be stored in a file, the first code keeps the order of
+/// version 1 files
+pub const LMS_ORDERS: [u8; 4] = [DEFAULT_LMS_LEN as u8, 2, 8, 16];
+
+/// Sample depth signalled in the header flags, `None` for the reserved code
+pub fn sample_depth_from_flags(flags: u8) -> Option<u8> {
+    let code = (flags & FLAGS_SAMPLE_DEPTH) >> FLAGS_SAMPLE_DEPTH_SHIFT;
+    SAMPLE_DEPTHS.get(code as usize).copied()
+}
+
+/// Header flag bits signalling the sample depth, `None` for unsupported depths
+#[cfg(feature = "alloc")]
+pub fn sample_depth_flags(sample_depth: u8) -> Option<u8> {
+    let code = SAMPLE_DEPTHS
+        .iter()
+        .position(|&depth| depth == sample_depth)?;
+    Some((code as u8) << FLAGS_SAMPLE_DEPTH_SHIFT)
+}
+
+/// LMS predictor order signalled in the header flags
+pub fn lms_order_from_flags(flags: u8) -> u8 {
+    LMS_ORDERS[((flags & FLAGS_LMS_ORDER) >> FLAGS_LMS_ORDER_SHIFT) as usize]
+}
+
+/// Header flag bits signalling the LMS predictor order, `None` for unsupported orders
+#[cfg(feature = "alloc")]
+pub fn lms_order_flags(lms_order: u8) -> Option<u8> {
+    let code = LMS_ORDERS.iter().position(|&order| order == lms_order)?;
+    Some((code as u8) << FLAGS_LMS_ORDER_SHIFT)
+}
+
+/// Stream parameters from the file header, without the metadata.
+/// Everything needed to locate and parse chunks, it can be read without allocating.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SeaFormat {
+    pub version: u8,
+    pub channels: u8,
+    pub chunk_size: u32,
+    pub frames_per_chunk: u16,
+    pub sample_rate: u32,
+    pub total_frames: u64,
+    pub flags: u8,
+}
+
+impl SeaFormat {
+    /// Reads the file header up to the metadata, returns the format and the metadata size
+    pub fn from_reader(reader: &mut Cursor) -> Result<(Self, u32), SeaError> {
+        let magic = read_u32_be(reader)?;
+        if magic != SEAC_MAGIC {
+            return Err(SeaError::InvalidFile {
+                reason: "not a SEA file",
+            });
+        }
+        let version = read_u8(reader)?;
+        if !(1..=MAX_VERSION).contains(&version) {
+            return Err(SeaError::UnsupportedVersion { version });
+        }
+        let channels = read_u8(reader)?;
+        let chunk_size = if version >= WIDE_HEADER_VERSION {
+            read_u32_le(reader)?
+        } else {
+            read_u16_le(reader)? as u32
+        };
+        let frames_per_chunk = read_u16_le(reader)?;
+        let sample_rate = read_u32_le(reader)?;
+        let total_frames = if version >= WIDE_HEADER_VERSION {
+            read_u64_le(reader)?
+        } else {
+            read_u32_le(reader)? as u64
+        };
+        let flags = if version >= 2 { read_u8(reader)? } else { 0 };
+        if flags & !KNOWN_FLAGS != 0 || sample_depth_from_flags(flags).is_none() {
+            return Err(SeaError::UnsupportedFlags { flags });
+        }
+        let metadata_size = read_u32_le(reader)?;
+
+        let format = Self {
+            version,
+            channels,
+            chunk_size,
+            frames_per_chunk,
+            sample_rate,
+            total_frames,
+            flags,
+        };
+
+        Ok((format, metadata_size))
+    }
+
+    pub fn chunk_crc(&self) -> bool {
+        self.flags & FLAG_CHUNK_CRC != 0
+    }
+
+    pub fn sync(&self) -> bool {
+        self.flags & FLAG_SYNC != 0
+    }
+
+    /// Chunks vary in size, `chunk_size` is the largest one. They cannot be located
+    /// without reading the chunks before them.
+    pub fn variable_chunks(&self) -> bool {
+        self.flags & FLAG_VARIABLE_CHUNKS != 0
+    }
+
+    /// Bits per sample of the encoded signal, 16 unless signalled otherwise
+    pub fn sample_depth(&self) -> u8 {
+        sample_depth_from_flags(self.flags).unwrap_or(16)
+    }
+
+    /// Number of past samples the LMS predictors are based on
+    pub fn lms_order(&self) -> usize {
+        lms_order_from_flags(self.flags) as usize
+    }
+
+    /// Size of a serialized LMS state of one channel
+    pub fn lms_size(&self) -> usize {
+        lms_size(self.sample_depth(), self.lms_order())
+    }
+
+    pub fn is_valid(&self) -> bool {
+        // every chunk holds at least its header and the LMS states
+        let min_chunk_size = self.chunk_header_size() + self.channels as usize * self.lms_size();
+        // scale factors, residual sizes and residuals take less than 3 bytes per sample,
+        // this keeps a corrupt size from allocating a huge chunk buffer
+        let max_chunk_size =
+            min_chunk_size + 3 * self.channels as usize * self.frames_per_chunk as usize;
+
+        self.flags & !KNOWN_FLAGS == 0
+            && sample_depth_from_flags(self.flags).is_some()
+            && self.channels > 0
+            && (min_chunk_size..=max_chunk_size).contains(&(self.chunk_size as usize))
+            && self.frames_per_chunk > 0
+            && self.sample_rate > 0
+    }
+
+    /// Size of the fixed chunk header, including the optional checksum
+    pub fn chunk_header_size(&self) -> usize {
+        if self.chunk_crc() {
+            8
+        } else {
+            4
+        }
+    }
+
+    /// Size of the sync header in front of each chunk, zero if sync framing is disabled
+    pub fn sync_header_size(&self) -> usize {
+        if self.sync() {
+            SYNC_HEADER_SIZE
+        } else {
+            0
+        }
+    }
+
+    /// Distance between the starts of two consecutive chunks in the file,
+    /// the largest distance for variable chunk sizes
+    pub fn chunk_stride(&self) -> usize {
+        self.sync_header_size() + self.chunk_size as usize
+    }
+}
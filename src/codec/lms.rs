@@ -1,97 +1,190 @@
+#[cfg(feature = "alloc")]
 use alloc::vec::Vec;
 
-pub const LMS_LEN: usize = 4;
+use super::common::{clamp_sample, sample_max};
 
+/// Predictor order of files that do not signal one
+pub const DEFAULT_LMS_LEN: usize = 4;
+/// Longest predictor, states are stored inline so the decoder does not allocate
+pub const MAX_LMS_LEN: usize = 16;
+
+/// Size of a serialized LMS state, history is stored with 16 or 32 bits depending on the sample depth
+pub const fn lms_size(sample_depth: u8, order: usize) -> usize {
+    if sample_depth > 16 {
+        order * (4 + 2)
+    } else {
+        order * (2 + 2)
+    }
+}
+
+/// Only the first `order` entries of `history` and `weights` are used, the oldest sample first
 #[derive(Debug, Clone)]
 pub struct SeaLMS {
-    pub history: [i32; LMS_LEN],
-    pub weights: [i32; LMS_LEN],
+    pub history: [i32; MAX_LMS_LEN],
+    pub weights: [i32; MAX_LMS_LEN],
+    order: u8,
+    // bits above 16 per sample, keeps the weight updates independent of the sample depth
+    depth_shift: u8,
 }
 
 const FLOATING_BITS: usize = 3;
 
 impl SeaLMS {
-    pub fn new() -> Self {
+    pub const fn new() -> Self {
         Self {
-            history: [0; LMS_LEN],
-            weights: [0; LMS_LEN],
+            history: [0; MAX_LMS_LEN],
+            weights: [0; MAX_LMS_LEN],
+            order: DEFAULT_LMS_LEN as u8,
+            depth_shift: 0,
         }
     }
 
-    pub fn init_vec(channels: u32) -> Vec<SeaLMS> {
+    #[cfg(feature = "alloc")]
+    pub fn init_vec(channels: u32, sample_depth: u8, order: usize) -> Vec<SeaLMS> {
         let mut lms_vec = Vec::with_capacity(channels as usize);
         for _ in 0..channels {
             let mut lms = SeaLMS {
-                history: [0; LMS_LEN],
-                weights: [0; LMS_LEN],
+                history: [0; MAX_LMS_LEN],
+                weights: [0; MAX_LMS_LEN],
+                order: order as u8,
+                depth_shift: sample_depth - 16,
             };
-            lms.weights[LMS_LEN - 2] = -(1 << (16 - FLOATING_BITS));
-            lms.weights[LMS_LEN - 1] = 1 << (17 - FLOATING_BITS);
+            // extrapolates the last two samples
+            lms.weights[order - 2] = -(1 << (16 - FLOATING_BITS));
+            lms.weights[order - 1] = 1 << (17 - FLOATING_BITS);
 
             lms_vec.push(lms);
         }
         lms_vec
     }
+
+    /// Number of past samples the prediction is based on
+    #[inline(always)]
+    pub fn order(&self) -> usize {
+        self.order as usize
+    }
+
+    fn prediction_sum(&self) -> i64 {
+        let order = self.order();
+        let mut sum: i64 = 0;
+
+        // wrapping arithmetic keeps corrupt LMS states from panicking,
+        // valid streams never overflow
+        for (&weight, &history) in self.weights[..order].iter().zip(&self.history[..order]) {
+            sum = sum.wrapping_add(weight as i64 * history as i64);
+        }
+
+        sum
+    }
+
+    /// Saturates instead of wrapping, valid streams stay within `i32`
     pub fn predict(&self) -> i32 {
-        let mut prediction: i32 = 0;
+        (self.prediction_sum() >> (16 - FLOATING_BITS)).clamp(i32::MIN as i64, i32::MAX as i64)
+            as i32
+    }
 
-        for i in 0..LMS_LEN {
-            prediction += self.weights[i] * self.history[i];
+    /// Prediction for the encoder, `None` if a decoder could overflow while computing it.
+    /// 16-bit streams with the default order have to stay within `i32` so that older decoders
+    /// reproduce them.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn checked_predict(&self) -> Option<i32> {
+        let order = self.order();
+        let sum = self.weights[..order]
+            .iter()
+            .zip(&self.history[..order])
+            .try_fold(0i64, |sum, (&weight, &history)| {
+                sum.checked_add(weight as i64 * history as i64)
+            })?;
+
+        if self.depth_shift == 0 && order == DEFAULT_LMS_LEN {
+            i32::try_from(sum)
+                .ok()
+                .map(|value| value >> (16 - FLOATING_BITS))
+        } else {
+            i32::try_from(sum >> (16 - FLOATING_BITS)).ok()
         }
+    }
 
-        prediction >> (16 - FLOATING_BITS)
+    /// Weight adjustment applied by `update` for the given residual
+    #[inline(always)]
+    pub fn update_delta(&self, residual: i32) -> i32 {
+        // long predictors adapt every weight, their steps are smaller to stay stable
+        let order_shift = (self.order as u32).trailing_zeros().saturating_sub(2);
+        residual >> (FLOATING_BITS as u32 + 1 + self.depth_shift as u32 + order_shift)
     }
 
-    pub fn update(&mut self, sample: i16, residual: i32) {
-        let delta = residual >> (FLOATING_BITS + 1);
-        for i in 0..LMS_LEN {
-            self.weights[i] += if self.history[i] < 0 { -delta } else { delta };
+    pub fn update(&mut self, sample: i32, residual: i32) {
+        let order = self.order();
+        let delta = self.update_delta(residual);
+        for (weight, &history) in self.weights[..order].iter_mut().zip(&self.history[..order]) {
+            let adjustment = if history < 0 { -delta } else { delta };
+            *weight = weight.wrapping_add(adjustment);
         }
 
-        self.history.copy_within(1.., 0);
-        self.history[LMS_LEN - 1] = sample as i32;
+        self.history.copy_within(1..order, 0);
+        self.history[order - 1] = sample;
     }
 
+    #[cfg(feature = "alloc")]
     pub fn get_weights_penalty(&self) -> u64 {
         let mut sum: i64 = 0;
 
-        for i in 0..LMS_LEN {
-            sum += self.weights[i] as i64 * self.weights[i] as i64;
+        for &weight in &self.weights[..self.order()] {
+            sum += weight as i64 * weight as i64;
         }
 
         let penalty = (sum >> 18) - 0x8ff;
         (penalty.max(0) as u64).pow(2)
     }
 
-    pub fn serialize(&self) -> [u8; LMS_LEN * 4] {
-        let mut output = [0u8; LMS_LEN * 4];
-
-        for i in 0..LMS_LEN {
-            let history_bytes = self.history[i].to_le_bytes();
-            output[i * 2] = history_bytes[0];
-            output[i * 2 + 1] = history_bytes[1];
-
-            let weights_bytes = self.weights[i].to_le_bytes();
-            output[LMS_LEN * 2 + i * 2] = weights_bytes[0];
-            output[LMS_LEN * 2 + i * 2 + 1] = weights_bytes[1];
+    /// Appends the history followed by the weights to `output`
+    #[cfg(feature = "alloc")]
+    pub fn serialize(&self, output: &mut Vec<u8>) {
+        let order = self.order();
+        for &history in &self.history[..order] {
+            if self.depth_shift == 0 {
+                output.extend_from_slice(&(history as i16).to_le_bytes());
+            } else {
+                output.extend_from_slice(&history.to_le_bytes());
+            }
         }
 
-        output
+        for &weight in &self.weights[..order] {
+            output.extend_from_slice(&(weight as i16).to_le_bytes());
+        }
     }
 
-    pub fn from_bytes(data: &[u8; LMS_LEN * 4]) -> Self {
-        let mut history = [0i32; LMS_LEN];
-        let mut weights = [0i32; LMS_LEN];
-
-        for i in 0..LMS_LEN {
-            let i16_history = i16::from_le_bytes([data[i * 2], data[i * 2 + 1]]);
-            history[i] = i16_history as i32;
+    /// Parses a state of `lms_size(sample_depth, order)` bytes. 32-bit history is clamped to
+    /// the sample range, which encoders never leave.
+    pub fn from_bytes(data: &[u8], sample_depth: u8, order: usize) -> Self {
+        let sample_max = sample_max(sample_depth);
+        let mut history = [0i32; MAX_LMS_LEN];
+        let mut weights = [0i32; MAX_LMS_LEN];
+
+        let history_size = lms_size(sample_depth, order) / order - 2;
+        let (history_bytes, weights_bytes) = data.split_at(order * history_size);
+
+        for i in 0..order {
+            let bytes = &history_bytes[i * history_size..(i + 1) * history_size];
+            history[i] = if history_size == 2 {
+                i16::from_le_bytes([bytes[0], bytes[1]]) as i32
+            } else {
+                clamp_sample(
+                    i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+                    sample_max,
+                )
+            };
 
-            let i16_weights =
-                i16::from_le_bytes([data[LMS_LEN * 2 + i * 2], data[LMS_LEN * 2 + i * 2 + 1]]);
-            weights[i] = i16_weights as i32;
+            weights[i] =
+                i16::from_le_bytes([weights_bytes[i * 2], weights_bytes[i * 2 + 1]]) as i32;
         }
 
-        SeaLMS { history, weights }
+        SeaLMS {
+            history,
+            weights,
+            order: order as u8,
+            depth_shift: sample_depth - 16,
+        }
     }
 }
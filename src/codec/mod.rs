@@ -1,12 +1,26 @@
 mod bits;
-mod chunk;
+pub mod chunk;
 pub mod common;
-mod decoder;
-mod dqt;
+mod crc;
+pub mod decoder;
+#[cfg(feature = "alloc")]
+pub mod dqt;
+pub mod dqt_values;
+#[cfg(feature = "alloc")]
 mod encoder_base;
+#[cfg(feature = "alloc")]
 mod encoder_cbr;
+#[cfg(feature = "alloc")]
+mod encoder_quality;
+#[cfg(feature = "alloc")]
 mod encoder_vbr;
-mod encoder_vbr_beam;
+#[cfg(feature = "alloc")]
+pub(crate) mod encoder_vbr_beam;
+#[cfg(feature = "alloc")]
 pub mod file;
+pub mod format;
 mod lms;
+#[cfg(feature = "alloc")]
 mod qt;
+mod stereo;
+pub mod sync;
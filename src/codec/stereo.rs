@@ -0,0 +1,97 @@
+#[cfg(feature = "alloc")]
+use super::{common::clamp_sample, lms::SeaLMS};
+
+/// Mid and side of a left and right sample. Mid drops the lowest bit of the sum, which the
+/// side keeps, so the pair converts back exactly. Side needs one bit more than the inputs.
+#[cfg(feature = "alloc")]
+#[inline(always)]
+pub fn to_mid_side(left: i32, right: i32) -> (i32, i32) {
+    ((left + right) >> 1, left - right)
+}
+
+/// Left and right of a mid and side sample, restoring the lowest bit of the sum from the side
+#[inline(always)]
+pub fn to_left_right(mid: i32, side: i32) -> (i32, i32) {
+    let left = mid + ((side + (side & 1)) >> 1);
+    (left, left - side)
+}
+
+/// Whether the first two channels are likely cheaper to encode as mid and side. The bits
+/// needed follow the logarithm of the prediction error, so the products of the absolute first
+/// order differences are compared. Scaling mid up and side down by the square root of two
+/// gives both pairs the same energy and leaves the product unchanged.
+///
+/// Chunks with a side sample outside the sample range stay left and right, as the side is
+/// coded at the sample depth of the file.
+#[cfg(feature = "alloc")]
+pub fn prefers_mid_side(samples: &[i32], channels: usize, sample_max: i32) -> bool {
+    // left, right, mid and side
+    let mut previous = [0i32; 4];
+    let mut differences = [0u64; 4];
+
+    for (frame_index, frame) in samples.chunks_exact(channels).enumerate() {
+        let (mid, side) = to_mid_side(frame[0], frame[1]);
+        if clamp_sample(side, sample_max) != side {
+            return false;
+        }
+        let current = [frame[0], frame[1], mid, side];
+        if frame_index > 0 {
+            for (difference, (value, previous)) in
+                differences.iter_mut().zip(current.iter().zip(previous))
+            {
+                *difference += value.abs_diff(previous) as u64;
+            }
+        }
+        previous = current;
+    }
+
+    let [left, right, mid, side] = differences.map(|difference| difference as f64);
+    mid * side < left * right
+}
+
+/// Converts the first two channels of interleaved samples to mid and side, which
+/// `prefers_mid_side` has to have accepted
+#[cfg(feature = "alloc")]
+pub fn samples_to_mid_side(samples: &mut [i32], channels: usize) {
+    for frame in samples.chunks_exact_mut(channels) {
+        (frame[0], frame[1]) = to_mid_side(frame[0], frame[1]);
+    }
+}
+
+/// Converts the history of the first two predictors when a chunk switches between left and
+/// right and mid and side, so the predictors continue from the previous chunk. The weights
+/// are kept, they adapt within a few frames.
+#[cfg(feature = "alloc")]
+pub fn convert_lms_history(lms: &mut [SeaLMS], mid_side: bool, sample_max: i32) {
+    let (first, rest) = lms.split_at_mut(1);
+    let (first, second) = (&mut first[0], &mut rest[0]);
+
+    for (a, b) in first.history.iter_mut().zip(second.history.iter_mut()) {
+        (*a, *b) = if mid_side {
+            let (mid, side) = to_mid_side(*a, *b);
+            (mid, clamp_sample(side, sample_max))
+        } else {
+            let (left, right) = to_left_right(*a, *b);
+            (
+                clamp_sample(left, sample_max),
+                clamp_sample(right, sample_max),
+            )
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mid_side_converts_back_exactly() {
+        let values = (-300..300).chain([-32768, -32767, 32766, 32767]);
+        for left in values.clone() {
+            for right in values.clone() {
+                let (mid, side) = to_mid_side(left, right);
+                assert_eq!(to_left_right(mid, side), (left, right));
+            }
+        }
+    }
+}
@@ -0,0 +1,131 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+#[cfg(feature = "alloc")]
+use crate::cursor::Cursor;
+
+#[cfg(feature = "alloc")]
+use super::{
+    common::{read_max, SeaError},
+    file::SeaFileHeader,
+};
+use super::{crc::Crc32, format::SeaFormat};
+
+pub const SYNC_MAGIC: [u8; 4] = *b"SEAS";
+
+// magic, stream parameters, frames in chunk, crc
+pub const SYNC_HEADER_SIZE: usize = 4 + 11 + 2 + 4;
+
+/// Compact copy of the stream parameters written in front of every chunk in sync
+/// framed streams, so a decoder can join the stream at any byte offset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeaSyncHeader {
+    pub version: u8,
+    pub flags: u8,
+    pub channels: u8,
+    pub chunk_size: u16,
+    pub frames_per_chunk: u16,
+    pub sample_rate: u32,
+    /// Frames stored in the following chunk
+    pub frames: u16,
+}
+
+impl SeaSyncHeader {
+    #[cfg(feature = "alloc")]
+    pub fn new(file_header: &SeaFileHeader, frames: u16) -> Self {
+        Self {
+            version: file_header.version,
+            flags: file_header.flags(),
+            channels: file_header.channels,
+            // larger chunks are rejected by the encoder in sync framed streams
+            chunk_size: file_header.chunk_size as u16,
+            frames_per_chunk: file_header.frames_per_chunk,
+            sample_rate: file_header.sample_rate,
+            frames,
+        }
+    }
+
+    /// Checks if the header belongs to a stream with the given format
+    pub fn matches(&self, format: &SeaFormat) -> bool {
+        self.version == format.version
+            && self.flags == format.flags
+            && self.channels == format.channels
+            && self.chunk_size as u32 == format.chunk_size
+            && self.frames_per_chunk == format.frames_per_chunk
+            && self.sample_rate == format.sample_rate
+            && (1..=self.frames_per_chunk).contains(&self.frames)
+    }
+
+    #[cfg(feature = "alloc")]
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut output = Vec::with_capacity(SYNC_HEADER_SIZE);
+
+        output.extend_from_slice(&SYNC_MAGIC);
+        output.push(self.version);
+        output.push(self.flags);
+        output.push(self.channels);
+        output.extend_from_slice(&self.chunk_size.to_le_bytes());
+        output.extend_from_slice(&self.frames_per_chunk.to_le_bytes());
+        output.extend_from_slice(&self.sample_rate.to_le_bytes());
+        output.extend_from_slice(&self.frames.to_le_bytes());
+
+        let mut crc = Crc32::new();
+        crc.update(&output);
+        output.extend_from_slice(&crc.finish().to_le_bytes());
+
+        output
+    }
+
+    /// Parses a sync header, returns `None` if the sync word or the checksum does not match
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != SYNC_HEADER_SIZE || bytes[..4] != SYNC_MAGIC {
+            return None;
+        }
+
+        let mut crc = Crc32::new();
+        crc.update(&bytes[..SYNC_HEADER_SIZE - 4]);
+        let stored_crc = u32::from_le_bytes(bytes[SYNC_HEADER_SIZE - 4..].try_into().unwrap());
+        if crc.finish() != stored_crc {
+            return None;
+        }
+
+        Some(Self {
+            version: bytes[4],
+            flags: bytes[5],
+            channels: bytes[6],
+            chunk_size: u16::from_le_bytes([bytes[7], bytes[8]]),
+            frames_per_chunk: u16::from_le_bytes([bytes[9], bytes[10]]),
+            sample_rate: u32::from_le_bytes(bytes[11..15].try_into().unwrap()),
+            frames: u16::from_le_bytes([bytes[15], bytes[16]]),
+        })
+    }
+
+    /// Reads the next sync header accepted by `accept`, skipping any bytes in front of it.
+    /// Returns `None` when the input ends before a header is found.
+    #[cfg(feature = "alloc")]
+    pub fn find(
+        reader: &mut Cursor,
+        accept: impl Fn(&Self) -> bool,
+    ) -> Result<Option<Self>, SeaError> {
+        let mut window = [0u8; SYNC_HEADER_SIZE];
+        if read_max(reader, &mut window)? < SYNC_HEADER_SIZE {
+            return Ok(None);
+        }
+
+        loop {
+            if let Some(header) = Self::parse(&window) {
+                if accept(&header) {
+                    return Ok(Some(header));
+                }
+            }
+
+            // slide the window by one byte
+            let mut next_byte = [0u8; 1];
+            if reader.read(&mut next_byte)? == 0 {
+                return Ok(None);
+            }
+            window.copy_within(1.., 0);
+            window[SYNC_HEADER_SIZE - 1] = next_byte[0];
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use alloc::{vec, vec::Vec};
+
+use crate::codec::{common::SeaError, format::SAMPLE_DEPTHS};
+
+/// How floating-point samples in the `[-1.0, 1.0)` range are reduced to the
+/// integer sample depth of the file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FloatConversion {
+    /// Rounds to the nearest value
+    #[default]
+    Round,
+    /// Adds triangular (TPDF) dither of up to one LSB before rounding, which makes
+    /// the quantization error independent of the signal
+    TpdfDither,
+    /// TPDF dither with first-order error feedback, which moves the quantization
+    /// noise towards high frequencies where it is less audible
+    NoiseShapedDither,
+}
+
+/// Converts floating-point samples to integers at a sample depth
+///
+/// Keeps the dither generator and the noise shaping state between calls, so a signal
+/// can be converted in pieces. The generator is seeded with a constant, the same input
+/// always gives the same output.
+pub struct SampleConverter {
+    conversion: FloatConversion,
+    sample_depth: u8,
+    channels: usize,
+    scale: f32,
+    min: f32,
+    max: f32,
+    rng: u32,
+    // last quantization error per channel, used by noise shaping
+    errors: Vec<f32>,
+}
+
+const RNG_SEED: u32 = 0x2545_f491;
+// keeps clipped samples from feeding back large errors
+const MAX_ERROR: f32 = 1.5;
+
+impl SampleConverter {
+    pub fn new(
+        channels: u8,
+        sample_depth: u8,
+        conversion: FloatConversion,
+    ) -> Result<Self, SeaError> {
+        if channels == 0 || !SAMPLE_DEPTHS.contains(&sample_depth) {
+            return Err(SeaError::InvalidParameters);
+        }
+
+        let scale = (1i32 << (sample_depth - 1)) as f32;
+        Ok(Self {
+            conversion,
+            sample_depth,
+            channels: channels as usize,
+            scale,
+            min: -scale,
+            max: scale - 1.0,
+            rng: RNG_SEED,
+            errors: vec![0.0; channels as usize],
+        })
+    }
+
+    pub fn sample_depth(&self) -> u8 {
+        self.sample_depth
+    }
+
+    pub fn conversion(&self) -> FloatConversion {
+        self.conversion
+    }
+
+    /// Converts interleaved samples starting at the first channel, appending them to `output`
+    pub fn convert(&mut self, input: &[f32], output: &mut Vec<i32>) {
+        output.reserve(input.len());
+        for (index, &sample) in input.iter().enumerate() {
+            output.push(self.convert_sample(index % self.channels, sample));
+        }
+    }
+
+    /// Converts a single sample of `channel`. NaN is converted to silence.
+    #[inline]
+    pub fn convert_sample(&mut self, channel: usize, sample: f32) -> i32 {
+        let value = if sample.is_nan() {
+            0.0
+        } else {
+            sample * self.scale
+        };
+
+        let quantized = match self.conversion {
+            FloatConversion::Round => libm::roundf(value).clamp(self.min, self.max),
+            FloatConversion::TpdfDither => {
+                libm::roundf(value + self.tpdf()).clamp(self.min, self.max)
+            }
+            FloatConversion::NoiseShapedDither => {
+                let shaped = value - self.errors[channel];
+                let quantized = libm::roundf(shaped + self.tpdf()).clamp(self.min, self.max);
+                self.errors[channel] = (quantized - shaped).clamp(-MAX_ERROR, MAX_ERROR);
+                quantized
+            }
+        };
+
+        quantized as i32
+    }
+
+    /// Triangular noise in the `(-1.0, 1.0)` LSB range
+    #[inline(always)]
+    fn tpdf(&mut self) -> f32 {
+        self.uniform() - self.uniform()
+    }
+
+    /// Uniform noise in the `[0.0, 1.0)` range, from a xorshift generator
+    #[inline(always)]
+    fn uniform(&mut self) -> f32 {
+        let mut x = self.rng;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.rng = x;
+        (x >> 8) as f32 / (1 << 24) as f32
+    }
+}
@@ -1,48 +1,119 @@
 use crate::codec::common::SeaError;
 
-pub enum Cursor<'inp> {
+#[cfg(feature = "std")]
+trait ReadSeek: std::io::Read + std::io::Seek {}
+
+#[cfg(feature = "std")]
+impl<T: std::io::Read + std::io::Seek> ReadSeek for T {}
+
+enum Source<'inp> {
     Slice(&'inp [u8]),
     #[cfg(feature = "std")]
     Reader(Box<dyn std::io::Read + 'inp>),
+    #[cfg(feature = "std")]
+    SeekableReader {
+        reader: Box<dyn ReadSeek + 'inp>,
+        start: u64,
+    },
+}
+
+pub struct Cursor<'inp> {
+    source: Source<'inp>,
+    // bytes consumed since the cursor was created
+    position: u64,
 }
+
 #[cfg(feature = "std")]
 impl<'inp> Cursor<'inp> {
     pub(crate) fn from_reader<R: std::io::Read + 'inp>(reader: R) -> Self {
-        Self::Reader(Box::new(reader))
+        Self {
+            source: Source::Reader(Box::new(reader)),
+            position: 0,
+        }
+    }
+
+    pub(crate) fn from_seekable_reader<R: std::io::Read + std::io::Seek + 'inp>(
+        mut reader: R,
+    ) -> Result<Self, SeaError> {
+        let start = reader.stream_position()?;
+        Ok(Self {
+            source: Source::SeekableReader {
+                reader: Box::new(reader),
+                start,
+            },
+            position: 0,
+        })
     }
 }
 
 impl<'inp> Cursor<'inp> {
     pub(crate) fn from_slice(data: &'inp [u8]) -> Self {
-        Self::Slice(data)
+        Self {
+            source: Source::Slice(data),
+            position: 0,
+        }
+    }
+
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    #[cfg(feature = "alloc")]
+    /// Moves the cursor to an absolute byte offset, relative to where it was created.
+    pub fn seek(&mut self, position: u64) -> Result<(), SeaError> {
+        match &mut self.source {
+            Source::Slice(_) => {}
+            #[cfg(feature = "std")]
+            Source::Reader(_) => return Err(SeaError::SeekNotSupported),
+            #[cfg(feature = "std")]
+            Source::SeekableReader { reader, start } => {
+                reader.seek(std::io::SeekFrom::Start(*start + position))?;
+            }
+        }
+        self.position = position;
+        Ok(())
+    }
+
+    fn remaining_slice(data: &'inp [u8], position: u64) -> &'inp [u8] {
+        usize::try_from(position)
+            .ok()
+            .and_then(|position| data.get(position..))
+            .unwrap_or_default()
     }
 
     pub fn read_exact(&mut self, result: &mut [u8]) -> Result<(), SeaError> {
-        match self {
-            Cursor::Slice(data) => {
-                if data.len() < result.len() {
-                    Err(SeaError::EndOfFile)
-                } else {
-                    let (r, remaining) = data.split_at(result.len());
-                    *data = remaining;
-                    result.copy_from_slice(r);
-                    Ok(())
+        match &mut self.source {
+            Source::Slice(data) => {
+                let remaining = Self::remaining_slice(data, self.position);
+                if remaining.len() < result.len() {
+                    return Err(SeaError::EndOfFile);
                 }
+                result.copy_from_slice(&remaining[..result.len()]);
             }
             #[cfg(feature = "std")]
-            Cursor::Reader(reader) => Ok(reader.read_exact(result)?),
+            Source::Reader(reader) => reader.read_exact(result)?,
+            #[cfg(feature = "std")]
+            Source::SeekableReader { reader, .. } => reader.read_exact(result)?,
         }
+        self.position += result.len() as u64;
+        Ok(())
     }
 
+    #[cfg(feature = "alloc")]
     pub fn read(&mut self, result: &mut [u8]) -> Result<usize, SeaError> {
-        match self {
-            Cursor::Slice(data) => {
-                let to_read = result.len().min(data.len());
-                self.read_exact(&mut result[..to_read])?;
-                Ok(to_read)
+        let bytes_read = match &mut self.source {
+            Source::Slice(data) => {
+                let remaining = Self::remaining_slice(data, self.position);
+                let to_read = result.len().min(remaining.len());
+                result[..to_read].copy_from_slice(&remaining[..to_read]);
+                to_read
             }
             #[cfg(feature = "std")]
-            Cursor::Reader(reader) => Ok(reader.read(result)?),
-        }
+            Source::Reader(reader) => reader.read(result)?,
+            #[cfg(feature = "std")]
+            Source::SeekableReader { reader, .. } => reader.read(result)?,
+        };
+        self.position += bytes_read as u64;
+        Ok(bytes_read)
     }
 }
@@ -1,71 +1,524 @@
-use alloc::vec::Vec;
+use alloc::{vec, vec::Vec};
 
 use crate::{
     codec::{
         common::SeaError,
+        decoder::{InterleavedWriter, PlanarWriter, SampleWriter},
         file::{SeaFile, SeaFileHeader},
     },
     cursor::Cursor,
+    metadata::SeaMetadata,
 };
 
+pub use crate::codec::decoder::OutputSample;
+
+/// What the decoder does with a chunk that fails to decode.
+///
+/// The end of a corrupt chunk is unknown when chunks vary in size, without sync framing
+/// decoding cannot continue and the error is always returned.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum CorruptChunkAction {
+    /// Return the error from `decode_frame`
+    #[default]
+    Error,
+    /// Drop the chunk, the output becomes shorter
+    Skip,
+    /// Replace the chunk with silence
+    Silence,
+    /// Replace the chunk with the last successfully decoded chunk, or silence if there is none
+    RepeatLast,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DecoderOptions {
+    pub on_corrupt_chunk: CorruptChunkAction,
+}
+
 pub struct SeaDecoder<'inp> {
     cursor: Cursor<'inp>,
     file: SeaFile,
-    frames_read: usize,
+    options: DecoderOptions,
+    // byte offset of the first chunk
+    data_start: u64,
+    frames_read: u64,
+    // frames to drop from the start of the next chunk after seeking
+    skip_frames: usize,
+    corrupt_chunk_count: u64,
+    last_corrupt_chunk: Option<u64>,
+    last_chunk: Vec<i32>,
 }
 
 impl<'inp> SeaDecoder<'inp> {
-    #[cfg(feature = "std")]
-    pub fn from_reader<R: std::io::Read + 'inp>(reader: R) -> Result<Self, SeaError> {
-        let mut cursor = Cursor::from_reader(reader);
-
+    fn new(mut cursor: Cursor<'inp>) -> Result<Self, SeaError> {
         let file = SeaFile::from_reader(&mut cursor)?;
+        let data_start = cursor.position();
+        Ok(Self::with_file(cursor, file, data_start))
+    }
 
-        Ok(Self {
+    fn new_sync(mut cursor: Cursor<'inp>) -> Result<Self, SeaError> {
+        let file = SeaFile::from_sync_reader(&mut cursor)?;
+        // the sync header of the first chunk has already been consumed
+        let data_start = cursor.position() - file.header.sync_header_size() as u64;
+        Ok(Self::with_file(cursor, file, data_start))
+    }
+
+    fn with_file(cursor: Cursor<'inp>, file: SeaFile, data_start: u64) -> Self {
+        Self {
+            data_start,
             cursor,
             file,
+            options: DecoderOptions::default(),
             frames_read: 0,
-        })
+            skip_frames: 0,
+            corrupt_chunk_count: 0,
+            last_corrupt_chunk: None,
+            last_chunk: Vec::new(),
+        }
+    }
+
+    /// Configures how chunks that fail to decode are handled.
+    ///
+    /// Each chunk carries its own LMS state, so decoding resumes at the next chunk
+    /// boundary after a corrupt chunk.
+    pub fn with_options(mut self, options: DecoderOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Number of chunks concealed so far.
+    pub fn corrupt_chunk_count(&self) -> u64 {
+        self.corrupt_chunk_count
+    }
+
+    /// Index of the most recently concealed chunk. Checking `corrupt_chunk_count` after each
+    /// `decode_frame` reports every index, without the decoder keeping a list on long streams.
+    pub fn last_corrupt_chunk(&self) -> Option<u64> {
+        self.last_corrupt_chunk
+    }
+
+    #[cfg(feature = "std")]
+    pub fn from_reader<R: std::io::Read + 'inp>(reader: R) -> Result<Self, SeaError> {
+        Self::new(Cursor::from_reader(reader))
+    }
+
+    /// Same as `from_reader`, but also enables `seek_to_frame`.
+    #[cfg(feature = "std")]
+    pub fn from_seekable_reader<R: std::io::Read + std::io::Seek + 'inp>(
+        reader: R,
+    ) -> Result<Self, SeaError> {
+        Self::new(Cursor::from_seekable_reader(reader)?)
     }
 
     pub fn from_slice(data: &'inp [u8]) -> Result<Self, SeaError> {
-        let mut cursor = Cursor::from_slice(data);
+        Self::new(Cursor::from_slice(data))
+    }
 
-        let file = SeaFile::from_reader(&mut cursor)?;
+    /// Joins a sync framed stream (see `EncoderSettings::sync`) at an arbitrary byte offset.
+    ///
+    /// Decoding starts at the first complete chunk, positions are counted from there.
+    /// Metadata is only stored in the file header, so it is not available.
+    #[cfg(feature = "std")]
+    pub fn from_sync_reader<R: std::io::Read + 'inp>(reader: R) -> Result<Self, SeaError> {
+        Self::new_sync(Cursor::from_reader(reader))
+    }
 
-        Ok(Self {
-            cursor,
-            file,
-            frames_read: 0,
-        })
+    /// Same as `from_sync_reader`, for streams already in memory.
+    pub fn from_sync_slice(data: &'inp [u8]) -> Result<Self, SeaError> {
+        Self::new_sync(Cursor::from_slice(data))
     }
 
+    /// Appends the next chunk to `result`, returns `false` at the end of the stream.
+    /// Higher resolution streams are reduced to 16 bits, see `decode_chunk_into` for
+    /// other sample types.
     pub fn decode_frame(&mut self, result: &mut Vec<i16>) -> Result<bool, SeaError> {
-        if self.file.header.total_frames != 0
-            && (self.file.header.total_frames as usize) <= self.frames_read
-        {
-            return Ok(false);
+        self.decode_frame_as(result)
+    }
+
+    pub(crate) fn decode_frame_as<S: OutputSample>(
+        &mut self,
+        result: &mut Vec<S>,
+    ) -> Result<bool, SeaError> {
+        let output_start = result.len();
+        result.resize(output_start + self.max_chunk_samples(), S::default());
+
+        let mut writer = InterleavedWriter {
+            output: &mut result[output_start..],
+            channels: self.file.header.channels as usize,
+            sample_depth: self.file.header.sample_depth,
+        };
+        let decoded = self.decode_next_chunk(&mut writer);
+        let samples_written = match decoded {
+            Ok(Some(samples_written)) => samples_written,
+            _ => 0,
+        };
+        result.truncate(output_start + samples_written);
+
+        Ok(decoded?.is_some())
+    }
+
+    /// Decodes the next chunk into `output` without allocating, once the first chunk
+    /// has been decoded. `output` needs room for `max_chunk_samples` samples, written
+    /// interleaved as `i16`, `i32` at the sample depth of the stream, or as `f32`
+    /// normalised to [-1, 1).
+    ///
+    /// Returns the number of interleaved samples written, zero at the end of the stream.
+    pub fn decode_chunk_into<S: OutputSample>(
+        &mut self,
+        output: &mut [S],
+    ) -> Result<usize, SeaError> {
+        if output.len() < self.max_chunk_samples() {
+            return Err(SeaError::InvalidParameters);
         }
 
-        let remaining_frames = if self.file.header.total_frames > 0 {
-            Some(self.file.header.total_frames as usize - self.frames_read)
-        } else {
-            None
+        let mut writer = InterleavedWriter {
+            output,
+            channels: self.file.header.channels as usize,
+            sample_depth: self.file.header.sample_depth,
         };
+        self.decode_chunk_to(&mut writer)
+    }
 
-        let samples_written =
-            self.file
-                .samples_from_reader(&mut self.cursor, remaining_frames, result)?;
+    /// Same as `decode_chunk_into`, but writes every channel into its own buffer.
+    /// `output` needs one buffer per channel, each with room for `frames_per_chunk` samples.
+    ///
+    /// Returns the number of frames written to each buffer, zero at the end of the stream.
+    pub fn decode_chunk_planar<S: OutputSample>(
+        &mut self,
+        output: &mut [&mut [S]],
+    ) -> Result<usize, SeaError> {
+        let channels = self.file.header.channels as usize;
+        let frames_per_chunk = self.file.header.frames_per_chunk as usize;
+        if output.len() != channels || output.iter().any(|plane| plane.len() < frames_per_chunk) {
+            return Err(SeaError::InvalidParameters);
+        }
+
+        let sample_depth = self.file.header.sample_depth;
+        let samples_written = self.decode_chunk_to(&mut PlanarWriter {
+            output,
+            sample_depth,
+        })?;
+        Ok(samples_written / channels)
+    }
+
+    fn decode_chunk_to(&mut self, output: &mut impl SampleWriter) -> Result<usize, SeaError> {
+        loop {
+            match self.decode_next_chunk(output)? {
+                None => return Ok(0),
+                // skipped corrupt chunk, or every frame was dropped after seeking
+                Some(0) => continue,
+                Some(samples_written) => return Ok(samples_written),
+            }
+        }
+    }
 
-        if samples_written == 0 {
-            Ok(false)
+    /// Largest number of samples a single chunk decodes to.
+    pub fn max_chunk_samples(&self) -> usize {
+        self.file.header.frames_per_chunk as usize * self.file.header.channels as usize
+    }
+
+    /// Returns the number of samples written, or `None` at the end of the stream.
+    /// `output` has to have room for a full chunk.
+    fn decode_next_chunk(
+        &mut self,
+        output: &mut impl SampleWriter,
+    ) -> Result<Option<usize>, SeaError> {
+        let total_frames = self.file.header.total_frames;
+        if total_frames != 0 && total_frames <= self.frames_read {
+            return Ok(None);
+        }
+
+        // never more than a chunk, so it fits `usize` on every target
+        let remaining_frames = (total_frames > 0).then(|| {
+            (total_frames - self.frames_read).min(self.file.header.frames_per_chunk as u64) as usize
+        });
+
+        let channels = self.file.header.channels as usize;
+        let repeat_last = self.options.on_corrupt_chunk == CorruptChunkAction::RepeatLast;
+        let mut output = SkipFrames {
+            inner: output,
+            frames: self.skip_frames,
+        };
+
+        let chunk_index = self.frames_read / self.file.header.frames_per_chunk as u64;
+        let chunk_offset = self.cursor.position();
+        let decoded = if repeat_last {
+            // chunks are validated before any sample is written,
+            // so a corrupt chunk leaves the last good chunk in place
+            self.last_chunk.resize(self.max_chunk_samples(), 0);
+            let mut recording = RecordingWriter {
+                inner: &mut output,
+                recorded: &mut self.last_chunk,
+                channels,
+            };
+            self.file
+                .decode_chunk(&mut self.cursor, remaining_frames, &mut recording)
         } else {
-            self.frames_read += samples_written / self.file.header.channels as usize;
-            Ok(true)
+            self.file
+                .decode_chunk(&mut self.cursor, remaining_frames, &mut output)
+        };
+
+        let decoded = decoded.map_err(|err| err.at_chunk(chunk_index, chunk_offset));
+        let (frames_decoded, frames_written) = match decoded {
+            Ok(0) => return Ok(None),
+            Ok(samples_written) => {
+                if repeat_last {
+                    self.last_chunk.truncate(samples_written);
+                }
+                let frames = samples_written / channels;
+                (frames, frames)
+            }
+            Err(err @ (SeaError::InvalidFrame { .. } | SeaError::ChecksumMismatch { .. })) => {
+                self.conceal_chunk(err, remaining_frames, &mut output)?
+            }
+            Err(err) => return Err(err),
+        };
+
+        let frames_written = frames_written.saturating_sub(self.skip_frames);
+        self.skip_frames = 0;
+        self.frames_read += frames_decoded as u64;
+        Ok(Some(frames_written * channels))
+    }
+
+    /// Writes replacement samples for a corrupt chunk.
+    /// Returns the number of frames it covers and the number of frames written.
+    fn conceal_chunk(
+        &mut self,
+        err: SeaError,
+        remaining_frames: Option<usize>,
+        output: &mut impl SampleWriter,
+    ) -> Result<(usize, usize), SeaError> {
+        let header = &self.file.header;
+        let channels = header.channels as usize;
+        let frames = (header.frames_per_chunk as usize).min(remaining_frames.unwrap_or(usize::MAX));
+
+        let lost_chunk_boundary = header.variable_chunks && !header.sync;
+        if self.options.on_corrupt_chunk == CorruptChunkAction::Error || lost_chunk_boundary {
+            return Err(err);
         }
+        self.corrupt_chunk_count += 1;
+        self.last_corrupt_chunk = Some(self.frames_read / header.frames_per_chunk as u64);
+
+        let replacement: &[i32] = match self.options.on_corrupt_chunk {
+            CorruptChunkAction::Skip => return Ok((frames, 0)),
+            CorruptChunkAction::RepeatLast => &self.last_chunk,
+            _ => &[],
+        };
+        // samples missing from the replacement are filled with silence
+        for frame in 0..frames {
+            for channel in 0..channels {
+                let sample = replacement.get(frame * channels + channel);
+                output.write(frame, channel, sample.copied().unwrap_or(0));
+            }
+        }
+
+        Ok((frames, frames))
+    }
+
+    /// Positions the decoder so that the next `decode_frame` call starts at `frame`.
+    ///
+    /// Chunks have a fixed size, so this jumps directly to the chunk containing `frame`
+    /// and discards the leading frames of that chunk once it is decoded.
+    /// Decoders created with `from_reader` cannot seek, use `from_seekable_reader` instead.
+    /// Files with variable chunk sizes cannot seek either.
+    pub fn seek_to_frame(&mut self, frame: u64) -> Result<(), SeaError> {
+        let header = &self.file.header;
+        if header.variable_chunks {
+            return Err(SeaError::SeekNotSupported);
+        }
+        if header.total_frames > 0 && frame > header.total_frames {
+            return Err(SeaError::InvalidParameters);
+        }
+
+        let frames_per_chunk = header.frames_per_chunk as u64;
+        let chunk_index = frame / frames_per_chunk;
+        let chunk_offset = self.data_start + chunk_index * header.chunk_stride() as u64;
+
+        self.cursor.seek(chunk_offset)?;
+        self.file.discard_pending_sync_header();
+        self.frames_read = chunk_index * frames_per_chunk;
+        self.skip_frames = (frame % frames_per_chunk) as usize;
+
+        Ok(())
+    }
+
+    /// Index of the next frame returned by `decode_frame`.
+    pub fn position(&self) -> u64 {
+        self.frames_read + self.skip_frames as u64
+    }
+
+    pub fn metadata(&self) -> &SeaMetadata {
+        &self.file.header.metadata
     }
 
     pub fn get_header(&self) -> SeaFileHeader {
         self.file.header.clone()
     }
+
+    /// Iterates over the remaining interleaved samples, reduced to 16 bits.
+    pub fn samples(&mut self) -> SeaSamples<'_, 'inp> {
+        SeaSamples {
+            buffer: vec![0; self.max_chunk_samples()],
+            decoder: self,
+            position: 0,
+            len: 0,
+            finished: false,
+        }
+    }
+
+    /// Turns the decoder into a reader of 16-bit little-endian interleaved PCM bytes.
+    #[cfg(feature = "std")]
+    pub fn into_pcm_reader(self) -> SeaPcmReader<'inp> {
+        SeaPcmReader {
+            buffer: vec![0; self.max_chunk_samples()],
+            decoder: self,
+            byte_position: 0,
+            len: 0,
+        }
+    }
+}
+
+/// Drops the leading frames of a chunk after seeking
+struct SkipFrames<'a, W> {
+    inner: &'a mut W,
+    frames: usize,
+}
+
+impl<W: SampleWriter> SampleWriter for SkipFrames<'_, W> {
+    #[inline(always)]
+    fn write(&mut self, frame: usize, channel: usize, sample: i32) {
+        if frame >= self.frames {
+            self.inner.write(frame - self.frames, channel, sample);
+        }
+    }
+}
+
+/// Keeps a copy of the decoded chunk for `CorruptChunkAction::RepeatLast`
+struct RecordingWriter<'a, W> {
+    inner: &'a mut W,
+    recorded: &'a mut [i32],
+    channels: usize,
+}
+
+impl<W: SampleWriter> SampleWriter for RecordingWriter<'_, W> {
+    #[inline(always)]
+    fn write(&mut self, frame: usize, channel: usize, sample: i32) {
+        self.recorded[frame * self.channels + channel] = sample;
+        self.inner.write(frame, channel, sample);
+    }
+}
+
+/// Iterator over decoded samples, created by `SeaDecoder::samples`.
+///
+/// Chunks are decoded into an internal buffer as needed. After an error the iterator
+/// returns `None`.
+pub struct SeaSamples<'a, 'inp> {
+    decoder: &'a mut SeaDecoder<'inp>,
+    buffer: Vec<i16>,
+    // next sample to return from buffer
+    position: usize,
+    len: usize,
+    finished: bool,
+}
+
+impl SeaSamples<'_, '_> {
+    /// Returns the samples of the next frame, one per channel.
+    pub fn next_frame(&mut self) -> Option<Result<&[i16], SeaError>> {
+        if let Err(err) = self.fill_buffer()? {
+            return Some(Err(err));
+        }
+
+        let channels = self.decoder.file.header.channels as usize;
+        let frame_start = self.position;
+        // a frame partially consumed by `next` is completed
+        self.position += channels - frame_start % channels;
+        Some(Ok(&self.buffer[frame_start..self.position]))
+    }
+
+    /// Decodes the next chunk once the buffer is used up, returns `None` at the end
+    fn fill_buffer(&mut self) -> Option<Result<(), SeaError>> {
+        if self.position < self.len {
+            return Some(Ok(()));
+        }
+        if self.finished {
+            return None;
+        }
+
+        self.position = 0;
+        self.len = 0;
+        match self.decoder.decode_chunk_into(&mut self.buffer) {
+            Ok(0) => {
+                self.finished = true;
+                None
+            }
+            Ok(len) => {
+                self.len = len;
+                Some(Ok(()))
+            }
+            Err(err) => {
+                self.finished = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+impl Iterator for SeaSamples<'_, '_> {
+    type Item = Result<i16, SeaError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Err(err) = self.fill_buffer()? {
+            return Some(Err(err));
+        }
+
+        let sample = self.buffer[self.position];
+        self.position += 1;
+        Some(Ok(sample))
+    }
+}
+
+/// Reader of 16-bit little-endian interleaved PCM bytes, created by
+/// `SeaDecoder::into_pcm_reader`. Decoding errors are returned as `InvalidData` wrapping the
+/// `SeaError`.
+#[cfg(feature = "std")]
+pub struct SeaPcmReader<'inp> {
+    decoder: SeaDecoder<'inp>,
+    buffer: Vec<i16>,
+    // next byte to return from buffer
+    byte_position: usize,
+    len: usize,
+}
+
+#[cfg(feature = "std")]
+impl SeaPcmReader<'_> {
+    pub fn decoder(&self) -> &SeaDecoder<'_> {
+        &self.decoder
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::io::Read for SeaPcmReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.byte_position == self.len * 2 {
+            self.byte_position = 0;
+            self.len = 0;
+            self.len = self
+                .decoder
+                .decode_chunk_into(&mut self.buffer)
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+        }
+
+        let bytes = self.buffer[self.byte_position / 2..self.len]
+            .iter()
+            .flat_map(|sample| sample.to_le_bytes())
+            .skip(self.byte_position % 2);
+
+        let mut written = 0;
+        for (dst, byte) in buf.iter_mut().zip(bytes) {
+            *dst = byte;
+            written += 1;
+        }
+        self.byte_position += written;
+
+        Ok(written)
+    }
 }
@@ -0,0 +1,326 @@
+//! Decoder for targets without a heap.
+//!
+//! `SeaEmbeddedDecoder` reads the encoded file directly from memory and keeps its state in
+//! fixed-size arrays. The dequantization tables are computed once, or generated at build time
+//! with the `static-tables` feature, and can be shared by any number of decoders.
+
+use core::{array, mem::MaybeUninit};
+
+use crate::{
+    codec::{
+        chunk::SeaChunkView,
+        common::SeaError,
+        decoder::{
+            decode_chunk, ChannelState, DequantLookup, InterleavedWriter, OutputSample,
+            PlanarWriter, SampleWriter,
+        },
+        dqt_values::{fill_dqt_row_all_residuals, residual_offset, DQT_ROW_LEN},
+        format::{SeaFormat, SAMPLE_DEPTHS},
+        sync::{SeaSyncHeader, SYNC_HEADER_SIZE},
+    },
+    cursor::Cursor,
+};
+
+#[cfg(feature = "static-tables")]
+include!(concat!(env!("OUT_DIR"), "/dqt_tables.rs"));
+
+/// Dequantization tables for a single scale factor size.
+///
+/// `SCALE_FACTORS` is the number of scale factor values, `1 << scale_factor_bits`, so
+/// `DequantTables<16>` decodes files encoded with the default 4 bit scale factors.
+/// The tables take 2 KiB per scale factor value. With the `static-tables` feature, the
+/// tables for 3 to 5 bit scale factors are available precomputed as `STATIC_TABLES_3`,
+/// `STATIC_TABLES_4` and `STATIC_TABLES_5`, for 16-bit files.
+#[derive(PartialEq)]
+pub struct DequantTables<const SCALE_FACTORS: usize> {
+    values: [[i32; DQT_ROW_LEN]; SCALE_FACTORS],
+    sample_depth: u8,
+}
+
+impl<const SCALE_FACTORS: usize> DequantTables<SCALE_FACTORS> {
+    /// Computes the tables for 16-bit files. Returns `InvalidParameters` if `SCALE_FACTORS`
+    /// is not a power of two between 2 and 256.
+    pub fn new() -> Result<Self, SeaError> {
+        Self::with_sample_depth(16)
+    }
+
+    /// Computes the tables for files with the given sample depth: 16, 20 or 24 bits.
+    ///
+    /// The tables are returned by value, which may not fit the stack of small targets.
+    /// [`DequantTables::init`] builds them in place instead.
+    pub fn with_sample_depth(sample_depth: u8) -> Result<Self, SeaError> {
+        let mut tables = Self {
+            values: [[0; DQT_ROW_LEN]; SCALE_FACTORS],
+            sample_depth,
+        };
+        tables.fill(sample_depth)?;
+        Ok(tables)
+    }
+
+    /// Computes the tables in `slot` for files with the given sample depth, without copying
+    /// them through the stack. `slot` can live in a `static` or any other memory.
+    pub fn init(slot: &mut MaybeUninit<Self>, sample_depth: u8) -> Result<&mut Self, SeaError> {
+        // SAFETY: all fields are integers, so all zero bytes are a valid value
+        let tables = unsafe {
+            slot.as_mut_ptr().write_bytes(0, 1);
+            slot.assume_init_mut()
+        };
+        tables.fill(sample_depth)?;
+        Ok(tables)
+    }
+
+    /// Recomputes the tables in place for files with the given sample depth.
+    pub fn fill(&mut self, sample_depth: u8) -> Result<(), SeaError> {
+        if !SCALE_FACTORS.is_power_of_two() || !(2..=256).contains(&SCALE_FACTORS) {
+            return Err(SeaError::InvalidParameters);
+        }
+        if !SAMPLE_DEPTHS.contains(&sample_depth) {
+            return Err(SeaError::InvalidParameters);
+        }
+
+        self.sample_depth = sample_depth;
+        let scale_factor_bits = self.scale_factor_bits() as usize;
+        for (index, row) in self.values.iter_mut().enumerate() {
+            fill_dqt_row_all_residuals(row, scale_factor_bits, sample_depth, index);
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "static-tables")]
+    const fn from_values(values: [[i32; DQT_ROW_LEN]; SCALE_FACTORS]) -> Self {
+        Self {
+            values,
+            sample_depth: 16,
+        }
+    }
+
+    pub fn scale_factor_bits(&self) -> u8 {
+        SCALE_FACTORS.trailing_zeros() as u8
+    }
+
+    pub fn sample_depth(&self) -> u8 {
+        self.sample_depth
+    }
+}
+
+impl<const SCALE_FACTORS: usize> DequantLookup for DequantTables<SCALE_FACTORS> {
+    #[inline(always)]
+    fn dequantize(&self, residual_size: u8, scale_factor: u8, quantized: u8) -> i32 {
+        self.values[scale_factor as usize]
+            [residual_offset(residual_size as usize) + quantized as usize]
+    }
+}
+
+/// Decodes a SEA file with `CHANNELS` channels from memory, without allocating.
+pub struct SeaEmbeddedDecoder<'a, const CHANNELS: usize> {
+    data: &'a [u8],
+    format: SeaFormat,
+    // byte offset of the first chunk
+    data_start: usize,
+    next_chunk: usize,
+    // byte offset of the next chunk, only tracked for variable chunk sizes
+    next_chunk_start: usize,
+    frames_read: u64,
+    channels: [ChannelState; CHANNELS],
+}
+
+impl<'a, const CHANNELS: usize> SeaEmbeddedDecoder<'a, CHANNELS> {
+    /// Reads the file header. Returns `InvalidParameters` if the file does not have
+    /// exactly `CHANNELS` channels. Metadata is skipped.
+    pub fn new(data: &'a [u8]) -> Result<Self, SeaError> {
+        let mut cursor = Cursor::from_slice(data);
+        let (format, metadata_size) = SeaFormat::from_reader(&mut cursor)?;
+        if !format.is_valid() {
+            return Err(SeaError::InvalidFile {
+                reason: "invalid stream parameters",
+            });
+        }
+        if format.channels as usize != CHANNELS {
+            return Err(SeaError::InvalidParameters);
+        }
+
+        let data_start = (cursor.position() as usize)
+            .checked_add(metadata_size as usize)
+            .filter(|data_start| *data_start <= data.len())
+            .ok_or(SeaError::InvalidFile {
+                reason: "metadata extends past the end of the file",
+            })?;
+
+        Ok(Self {
+            data,
+            format,
+            data_start,
+            next_chunk: 0,
+            next_chunk_start: data_start,
+            frames_read: 0,
+            channels: array::from_fn(|_| ChannelState::new()),
+        })
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.format.sample_rate
+    }
+
+    /// Bits per sample of the file, the dequantization tables have to match it
+    pub fn sample_depth(&self) -> u8 {
+        self.format.sample_depth()
+    }
+
+    /// Total frames per channel, zero for streams of unknown length
+    pub fn total_frames(&self) -> u64 {
+        self.format.total_frames
+    }
+
+    /// Largest number of samples a single chunk decodes to.
+    pub fn max_chunk_samples(&self) -> usize {
+        self.format.frames_per_chunk as usize * CHANNELS
+    }
+
+    /// Index of the next frame returned by `decode_chunk`.
+    pub fn position(&self) -> u64 {
+        self.frames_read
+    }
+
+    /// Restarts decoding from the first chunk, for looping playback.
+    pub fn rewind(&mut self) {
+        self.next_chunk = 0;
+        self.next_chunk_start = self.data_start;
+        self.frames_read = 0;
+    }
+
+    /// Decodes the next chunk into `output`, which needs room for `max_chunk_samples` samples,
+    /// written interleaved as `i16`, `i32` at the sample depth of the file, or as `f32`
+    /// normalised to [-1, 1). `tables` have to match the scale factor size and the sample
+    /// depth of the chunk, otherwise `SeaError::TablesMismatch` names the tables needed.
+    ///
+    /// Returns the number of interleaved samples written, zero at the end of the file.
+    /// After an error, the next call continues with the following chunk. In files with
+    /// variable chunk sizes the following chunk cannot be found, decoding ends instead.
+    pub fn decode_chunk<const SCALE_FACTORS: usize, S: OutputSample>(
+        &mut self,
+        tables: &DequantTables<SCALE_FACTORS>,
+        output: &mut [S],
+    ) -> Result<usize, SeaError> {
+        if output.len() < self.max_chunk_samples() {
+            return Err(SeaError::InvalidParameters);
+        }
+
+        let mut writer = InterleavedWriter {
+            output,
+            channels: CHANNELS,
+            sample_depth: self.format.sample_depth(),
+        };
+        self.decode_chunk_to(tables, &mut writer)
+    }
+
+    /// Same as `decode_chunk`, but writes every channel into its own buffer, each with room
+    /// for `frames_per_chunk` samples.
+    ///
+    /// Returns the number of frames written to each buffer, zero at the end of the file.
+    pub fn decode_chunk_planar<const SCALE_FACTORS: usize, S: OutputSample>(
+        &mut self,
+        tables: &DequantTables<SCALE_FACTORS>,
+        output: &mut [&mut [S]; CHANNELS],
+    ) -> Result<usize, SeaError> {
+        let frames_per_chunk = self.format.frames_per_chunk as usize;
+        if output.iter().any(|plane| plane.len() < frames_per_chunk) {
+            return Err(SeaError::InvalidParameters);
+        }
+
+        let mut writer = PlanarWriter {
+            output,
+            sample_depth: self.format.sample_depth(),
+        };
+        let samples_written = self.decode_chunk_to(tables, &mut writer)?;
+        Ok(samples_written / CHANNELS)
+    }
+
+    fn decode_chunk_to<const SCALE_FACTORS: usize>(
+        &mut self,
+        tables: &DequantTables<SCALE_FACTORS>,
+        output: &mut impl SampleWriter,
+    ) -> Result<usize, SeaError> {
+        let total_frames = self.format.total_frames;
+        if total_frames != 0 && self.frames_read >= total_frames {
+            return Ok(0);
+        }
+
+        let chunk_start = if self.format.variable_chunks() {
+            self.next_chunk_start
+        } else {
+            self.data_start + self.next_chunk * self.format.chunk_stride()
+        };
+        if chunk_start >= self.data.len() {
+            return Ok(0);
+        }
+        let chunk_index = self.next_chunk as u64;
+        let at_chunk = |err: SeaError| err.at_chunk(chunk_index, chunk_start as u64);
+        // a corrupt chunk of a variable chunk size file ends decoding
+        let mut next_chunk_start = self.data.len();
+        let mut frames = 0;
+        let parsed = self
+            .locate_chunk(chunk_start)
+            .and_then(|(encoded, remaining_frames)| {
+                let encoded = if self.format.variable_chunks() {
+                    let chunk_size =
+                        SeaChunkView::encoded_size(encoded, &self.format, remaining_frames)?;
+                    let encoded = encoded.get(..chunk_size).ok_or(SeaError::INVALID_FRAME)?;
+                    next_chunk_start = chunk_start + self.format.sync_header_size() + chunk_size;
+                    encoded
+                } else {
+                    encoded
+                };
+                frames = (self.format.frames_per_chunk as usize)
+                    .min(remaining_frames.unwrap_or(usize::MAX)) as u64;
+                SeaChunkView::from_slice(encoded, &self.format, remaining_frames)
+            });
+
+        // the chunk stays next, so decoding can be retried with matching tables
+        if let Ok(chunk) = &parsed {
+            if chunk.scale_factor_bits != tables.scale_factor_bits()
+                || chunk.sample_depth != tables.sample_depth()
+            {
+                return Err(SeaError::TablesMismatch {
+                    scale_factor_bits: chunk.scale_factor_bits,
+                    sample_depth: chunk.sample_depth,
+                });
+            }
+        }
+
+        // a corrupt chunk is skipped by the next call
+        self.next_chunk += 1;
+        if self.format.variable_chunks() {
+            self.next_chunk_start = next_chunk_start;
+        }
+        self.frames_read += frames;
+        let chunk = parsed.map_err(at_chunk)?;
+
+        Ok(decode_chunk(&chunk, tables, &mut self.channels, output))
+    }
+
+    /// Returns the encoded chunk starting at `chunk_start` and the number of frames it
+    /// can hold, after checking the sync header of sync framed files.
+    fn locate_chunk(&self, chunk_start: usize) -> Result<(&'a [u8], Option<usize>), SeaError> {
+        let chunk_end = (chunk_start + self.format.chunk_stride()).min(self.data.len());
+        let encoded = &self.data[chunk_start..chunk_end];
+
+        // never more than a chunk, so it fits `usize` on every target
+        let total_frames = self.format.total_frames;
+        let remaining_frames = (total_frames != 0).then(|| {
+            (total_frames - self.frames_read).min(self.format.frames_per_chunk as u64) as usize
+        });
+        if !self.format.sync() {
+            return Ok((encoded, remaining_frames));
+        }
+
+        let sync_header = encoded
+            .get(..SYNC_HEADER_SIZE)
+            .and_then(SeaSyncHeader::parse)
+            .filter(|sync_header| sync_header.matches(&self.format))
+            .ok_or(SeaError::INVALID_FRAME)?;
+        let frames = sync_header.frames as usize;
+        let remaining_frames = remaining_frames.map_or(frames, |remaining| remaining.min(frames));
+
+        Ok((&encoded[SYNC_HEADER_SIZE..], Some(remaining_frames)))
+    }
+}
@@ -1,8 +1,15 @@
-use alloc::{rc::Rc, string::String};
+use alloc::{rc::Rc, vec::Vec};
 
-use crate::codec::{
-    common::SeaError,
-    file::{SeaFile, SeaFileHeader},
+use crate::{
+    codec::{
+        common::{clamp_sample, sample_max, SeaError},
+        encoder_vbr_beam::MAX_PERIOD_FRAMES,
+        file::{SeaFile, SeaFileHeader},
+        format::{LMS_ORDERS, SAMPLE_DEPTHS},
+        sync::SYNC_HEADER_SIZE,
+    },
+    convert::{FloatConversion, SampleConverter},
+    metadata::{SeaMetadata, MAX_METADATA_SIZE},
 };
 
 pub enum SeaEncoderState {
@@ -11,6 +18,16 @@ pub enum SeaEncoderState {
     Finished,
 }
 
+/// Quality every scale factor period of every channel is encoded with, in dB
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QualityTarget {
+    /// Signal to noise ratio, relative to the energy of the period
+    Snr(f32),
+    /// Peak signal to noise ratio, relative to a full scale signal, so quiet
+    /// passages need fewer bits
+    Psnr(f32),
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct EncoderSettings {
     pub scale_factor_bits: u8,
@@ -18,9 +35,35 @@ pub struct EncoderSettings {
     pub residual_bits: f32, // 1-8
     pub frames_per_chunk: u16,
     pub vbr: bool,
-    /// VBR encoder effort. Zero is the fast rate-allocation and scalar encoder;
-    /// one through six enable the higher-quality residual-path beam search.
+    /// Encoder effort, for both CBR and VBR. Zero is the fast greedy encoder; one through six
+    /// enable the higher-quality residual-path beam search, which needs `scale_factor_frames`
+    /// of at most 20. CBR output keeps its size, only the chosen residuals change.
     pub vbr_residual_beam_width: u8,
+    /// Stores a CRC-32 in every chunk, so the decoder can detect corrupted data.
+    /// Requires file version 2.
+    pub checksum: bool,
+    /// Prefixes every chunk with a sync word and the stream parameters, so decoders can
+    /// join the stream at any point. Requires file version 2.
+    pub sync: bool,
+    /// Bits per sample stored in the file: 16, 20 or 24. Depths above 16 require file version 2.
+    pub sample_depth: u8,
+    /// How `f32` input is reduced to the sample depth. Has no effect on integer input.
+    pub float_conversion: FloatConversion,
+    /// Stores the total frame count with 64 bits, which requires file version 3.
+    /// `SeaEncoder` enables it when the passed frame count does not fit 32 bits, set it for
+    /// stream encoders that may run for more than `u32::MAX` frames.
+    pub long_frame_count: bool,
+    /// Encodes with the smallest residual sizes meeting a quality target instead of a
+    /// bitrate. `residual_bits`, `vbr` and `vbr_residual_beam_width` are ignored then.
+    /// Chunks vary in size, which requires file version 2 and disables seeking.
+    pub quality_target: Option<QualityTarget>,
+    /// Encodes the first two channels as mid and side in chunks where that is cheaper,
+    /// which helps correlated stereo. Requires file version 2, has no effect on mono.
+    pub mid_side: bool,
+    /// Number of past samples the LMS predictors use: 2, 4, 8 or 16. Short predictors suit
+    /// speech at low bitrates, long ones predict music better. Orders other than 4 require
+    /// file version 2.
+    pub lms_order: u8,
 }
 
 impl Default for EncoderSettings {
@@ -32,10 +75,187 @@ impl Default for EncoderSettings {
             residual_bits: 3.0,
             vbr: false,
             vbr_residual_beam_width: 0,
+            checksum: false,
+            sync: false,
+            sample_depth: 16,
+            float_conversion: FloatConversion::Round,
+            long_frame_count: false,
+            quality_target: None,
+            mid_side: false,
+            lms_order: 4,
         }
     }
 }
 
+impl EncoderSettings {
+    /// Checks that the settings can be encoded.
+    /// Returns `SeaError::InvalidSettings` naming the first invalid field.
+    pub fn validate(&self) -> Result<(), SeaError> {
+        let invalid = |field, reason| Err(SeaError::InvalidSettings { field, reason });
+
+        if !(200..=32000).contains(&self.frames_per_chunk) {
+            return invalid("frames_per_chunk", "must be between 200 and 32000");
+        }
+        if !(3..=5).contains(&self.scale_factor_bits) {
+            return invalid("scale_factor_bits", "must be between 3 and 5");
+        }
+        if self.scale_factor_frames == 0
+            || !self
+                .frames_per_chunk
+                .is_multiple_of(self.scale_factor_frames as u16)
+        {
+            return invalid(
+                "scale_factor_frames",
+                "must be a divisor of frames_per_chunk",
+            );
+        }
+        if self.vbr_residual_beam_width > 0
+            && self.quality_target.is_none()
+            && self.scale_factor_frames as usize > MAX_PERIOD_FRAMES
+        {
+            return invalid(
+                "scale_factor_frames",
+                "must be at most 20 with a residual beam width",
+            );
+        }
+        if self.vbr {
+            if !(1.5..=8.0).contains(&self.residual_bits) {
+                return invalid("residual_bits", "must be between 1.5 and 8.0 with VBR");
+            }
+        } else if libm::truncf(self.residual_bits) != self.residual_bits
+            || !(1.0..=8.0).contains(&self.residual_bits)
+        {
+            return invalid(
+                "residual_bits",
+                "must be an integer between 1 and 8 without VBR",
+            );
+        }
+        if !SAMPLE_DEPTHS.contains(&self.sample_depth) {
+            return invalid("sample_depth", "must be 16, 20 or 24");
+        }
+        if !LMS_ORDERS.contains(&self.lms_order) {
+            return invalid("lms_order", "must be 2, 4, 8 or 16");
+        }
+        if let Some(QualityTarget::Snr(db) | QualityTarget::Psnr(db)) = self.quality_target {
+            if !(1.0..=200.0).contains(&db) {
+                return invalid("quality_target", "must be between 1 and 200 dB");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Derives VBR settings that encode `channels` at `sample_rate` with `bitrate` bits per
+    /// second, framing overhead included. Chunk size, checksum, sync and effort settings are kept.
+    ///
+    /// The result is exact up to the rounding of chunk sizes to whole bytes. Returns
+    /// `InvalidSettings` for the `bitrate` field when it gives less than 1.5 or more than 8 bits
+    /// per sample, and `InvalidParameters` without channels or sample rate.
+    pub fn with_target_bitrate(
+        self,
+        bitrate: u32,
+        channels: u8,
+        sample_rate: u32,
+    ) -> Result<Self, SeaError> {
+        if channels == 0 || sample_rate == 0 {
+            return Err(SeaError::InvalidParameters);
+        }
+
+        let frames_per_chunk = self.frames_per_chunk as f64;
+        let chunk_bits = bitrate as f64 * frames_per_chunk / sample_rate as f64;
+        let framing_bytes =
+            if self.checksum { 8 } else { 4 } + if self.sync { SYNC_HEADER_SIZE } else { 0 };
+        // VBR residual bits already account for the LMS states, scale factors and residual sizes
+        let residual_bits =
+            (chunk_bits - framing_bytes as f64 * 8.0) / (frames_per_chunk * channels as f64);
+        let invalid = |reason| {
+            Err(SeaError::InvalidSettings {
+                field: "bitrate",
+                reason,
+            })
+        };
+        if residual_bits < 1.5 {
+            return invalid("is below 1.5 bits per sample");
+        }
+        if residual_bits > 8.0 {
+            return invalid("is above 8 bits per sample");
+        }
+        let residual_bits = residual_bits as f32;
+
+        // sparse scale factors leave more bits for residuals at low rates,
+        // dense ones track the signal better once residuals are wide
+        let spacing = if residual_bits < 4.5 {
+            40
+        } else if residual_bits < 6.0 {
+            20
+        } else {
+            10
+        };
+        // the residual beam search is limited to short periods
+        let spacing = if self.vbr_residual_beam_width > 0 {
+            spacing.min(MAX_PERIOD_FRAMES as u16)
+        } else {
+            spacing
+        };
+        let scale_factor_frames = (1..=spacing)
+            .rev()
+            .find(|frames| self.frames_per_chunk.is_multiple_of(*frames))
+            .unwrap_or(1) as u8;
+
+        Ok(Self {
+            residual_bits,
+            scale_factor_frames,
+            vbr: true,
+            ..self
+        })
+    }
+}
+
+/// Sample type encoders accept, converted to the sample depth of the file
+pub trait InputSample: Copy {
+    fn to_sample(self, converter: &mut SampleConverter, channel: usize) -> i32;
+}
+
+impl InputSample for i16 {
+    /// Scaled up to the sample depth
+    #[inline(always)]
+    fn to_sample(self, converter: &mut SampleConverter, _channel: usize) -> i32 {
+        (self as i32) << (converter.sample_depth() - 16)
+    }
+}
+
+impl InputSample for i32 {
+    /// Already at the sample depth, out of range values are clamped
+    #[inline(always)]
+    fn to_sample(self, converter: &mut SampleConverter, _channel: usize) -> i32 {
+        clamp_sample(self, sample_max(converter.sample_depth()))
+    }
+}
+
+impl InputSample for f32 {
+    /// In the `[-1.0, 1.0)` range, converted as set by `EncoderSettings::float_conversion`
+    #[inline(always)]
+    fn to_sample(self, converter: &mut SampleConverter, channel: usize) -> i32 {
+        converter.convert_sample(channel, self)
+    }
+}
+
+/// Converts interleaved `samples` to the sample depth of the file, replacing the contents of `output`
+fn convert_samples<S: InputSample>(
+    samples: &[S],
+    converter: &mut SampleConverter,
+    channels: usize,
+    output: &mut Vec<i32>,
+) {
+    output.clear();
+    output.extend(
+        samples
+            .iter()
+            .enumerate()
+            .map(|(index, sample)| sample.to_sample(converter, index % channels)),
+    );
+}
+
 trait InternalWrite {
     fn write_all(&mut self, buf: &[u8]) -> Result<(), SeaError>;
 }
@@ -55,38 +275,60 @@ impl InternalWrite for &mut alloc::vec::Vec<u8> {
     }
 }
 
-pub struct SeaEncoder<'inp> {
-    data: &'inp [i16],
+/// Stores `metadata` in the file header, which is only possible before the header is written
+fn set_metadata(
+    file: &mut SeaFile,
+    state: &SeaEncoderState,
+    metadata: SeaMetadata,
+) -> Result<(), SeaError> {
+    if !matches!(state, SeaEncoderState::Start) {
+        return Err(SeaError::InvalidParameters);
+    }
+    if metadata.serialize().len() > MAX_METADATA_SIZE {
+        return Err(SeaError::MetadataTooLarge);
+    }
+
+    file.header.metadata = Rc::new(metadata);
+    Ok(())
+}
+
+pub struct SeaEncoder<'inp, S: InputSample = i16> {
+    data: &'inp [S],
     file: SeaFile,
+    converter: SampleConverter,
+    // input converted to the sample depth, reused between chunks
+    chunk_samples: Vec<i32>,
     state: SeaEncoderState,
-    written_frames: u32,
-    passed_total_frames: Option<u32>,
+    written_frames: u64,
+    passed_total_frames: Option<u64>,
 }
 
-impl<'inp> SeaEncoder<'inp> {
+impl<'inp, S: InputSample> SeaEncoder<'inp, S> {
     pub fn from_slice(
         channels: u8,
         sample_rate: u32,
-        total_frames: Option<u32>,
+        total_frames: Option<u64>,
         settings: EncoderSettings,
-        data: &'inp [i16],
+        data: &'inp [S],
     ) -> Result<Self, SeaError> {
-        let header = SeaFileHeader {
-            version: 1,
-            channels,
-            chunk_size: 0, // will be set later by the first chunk
-            frames_per_chunk: settings.frames_per_chunk,
-            sample_rate,
-            total_frames: total_frames.unwrap_or(0),
-            metadata: Rc::new(String::new()),
-        };
+        settings.validate()?;
+        if channels == 0 {
+            return Err(SeaError::InvalidParameters);
+        }
+
+        let header =
+            SeaFileHeader::new(channels, sample_rate, total_frames.unwrap_or(0), &settings);
 
         let file = SeaFile::new(header, &settings)?;
+        let converter =
+            SampleConverter::new(channels, settings.sample_depth, settings.float_conversion)?;
 
         let state = SeaEncoderState::Start;
 
         Ok(SeaEncoder {
             file,
+            converter,
+            chunk_samples: Vec::new(),
             state,
             data,
             written_frames: 0,
@@ -94,7 +336,14 @@ impl<'inp> SeaEncoder<'inp> {
         })
     }
 
-    fn read_samples(&mut self, max_sample_count: usize) -> Result<&'inp [i16], SeaError> {
+    /// Stores `metadata` in the file header. Must be called before the first `encode_frame`.
+    /// Returns `SeaError::MetadataTooLarge` above `MAX_METADATA_SIZE` serialized bytes.
+    pub fn with_metadata(mut self, metadata: SeaMetadata) -> Result<Self, SeaError> {
+        set_metadata(&mut self.file, &self.state, metadata)?;
+        Ok(self)
+    }
+
+    fn read_samples(&mut self, max_sample_count: usize) -> Result<&'inp [S], SeaError> {
         let max_to_read = self.data.len().min(max_sample_count);
 
         if max_to_read == 0 {
@@ -129,7 +378,7 @@ impl<'inp> SeaEncoder<'inp> {
         if matches!(self.state, SeaEncoderState::Start) {
             if let Some(total_frames) = self.passed_total_frames {
                 if total_frames == 0 {
-                    writer.write_all(&self.file.header.serialize())?;
+                    writer.write_all(&self.file.empty_header()?)?;
                     self.state = SeaEncoderState::WritingFrames;
                 }
             }
@@ -137,8 +386,8 @@ impl<'inp> SeaEncoder<'inp> {
 
         let channels = self.file.header.channels;
         let frames = if self.file.header.total_frames > 0 {
-            (self.file.header.frames_per_chunk as usize)
-                .min(self.file.header.total_frames as usize - self.written_frames as usize)
+            (self.file.header.total_frames - self.written_frames)
+                .min(self.file.header.frames_per_chunk as u64) as usize
         } else {
             self.file.header.frames_per_chunk as usize
         };
@@ -150,12 +399,18 @@ impl<'inp> SeaEncoder<'inp> {
         let eof: bool = samples.is_empty() || samples.len() < full_size_samples;
 
         if !samples.is_empty() {
-            let encoded_chunk = self.file.make_chunk(samples)?;
+            convert_samples(
+                samples,
+                &mut self.converter,
+                channels as usize,
+                &mut self.chunk_samples,
+            );
+            let encoded_chunk = self.file.make_chunk(&self.chunk_samples)?;
 
-            if eof {
-                assert!(encoded_chunk.len() <= self.file.header.chunk_size as usize);
+            if eof || self.file.header.variable_chunks {
+                assert!(encoded_chunk.len() <= self.file.header.chunk_stride());
             } else {
-                assert_eq!(encoded_chunk.len(), self.file.header.chunk_size as usize);
+                assert_eq!(encoded_chunk.len(), self.file.header.chunk_stride());
             }
 
             // we need to write file header after the first chunk is generated
@@ -165,7 +420,7 @@ impl<'inp> SeaEncoder<'inp> {
             }
 
             writer.write_all(&encoded_chunk)?;
-            self.written_frames += frames as u32;
+            self.written_frames += frames as u64;
         }
 
         if eof {
@@ -180,3 +435,369 @@ impl<'inp> SeaEncoder<'inp> {
         Ok(())
     }
 }
+
+/// Encoder for live sources, which accepts samples as they become available.
+///
+/// Completed chunks are written as soon as `frames_per_chunk` frames are buffered,
+/// and the remaining short chunk is written by `finalize`.
+/// The header is written in streaming mode, as the final frame count is not known up front.
+/// Use `finalize_seekable` to patch the frame count into the header when the output allows it.
+pub struct SeaStreamEncoder {
+    file: SeaFile,
+    state: SeaEncoderState,
+    converter: SampleConverter,
+    // converted to the sample depth of the file
+    pending: Vec<i32>,
+    written_frames: u64,
+    written_bytes: u64,
+}
+
+impl SeaStreamEncoder {
+    pub fn new(
+        channels: u8,
+        sample_rate: u32,
+        settings: EncoderSettings,
+    ) -> Result<Self, SeaError> {
+        settings.validate()?;
+        if channels == 0 {
+            return Err(SeaError::InvalidParameters);
+        }
+
+        let header = SeaFileHeader::new(channels, sample_rate, 0, &settings);
+
+        let chunk_samples = settings.frames_per_chunk as usize * channels as usize;
+
+        Ok(SeaStreamEncoder {
+            file: SeaFile::new(header, &settings)?,
+            state: SeaEncoderState::Start,
+            converter: SampleConverter::new(
+                channels,
+                settings.sample_depth,
+                settings.float_conversion,
+            )?,
+            pending: Vec::with_capacity(chunk_samples),
+            written_frames: 0,
+            written_bytes: 0,
+        })
+    }
+
+    /// Stores `metadata` in the file header. Must be called before the first chunk is written.
+    /// Returns `SeaError::MetadataTooLarge` above `MAX_METADATA_SIZE` serialized bytes.
+    pub fn with_metadata(mut self, metadata: SeaMetadata) -> Result<Self, SeaError> {
+        set_metadata(&mut self.file, &self.state, metadata)?;
+        Ok(self)
+    }
+
+    /// Number of frames written to the output so far.
+    pub fn written_frames(&self) -> u64 {
+        self.written_frames
+    }
+
+    #[cfg(feature = "std")]
+    pub fn push_samples<S: InputSample>(
+        &mut self,
+        samples: &[S],
+        mut writer: impl std::io::Write,
+    ) -> Result<(), SeaError> {
+        self.push_samples_inner(samples, &mut writer)
+    }
+
+    #[cfg(not(feature = "std"))]
+    pub fn push_samples<S: InputSample>(
+        &mut self,
+        samples: &[S],
+        mut writer: &mut alloc::vec::Vec<u8>,
+    ) -> Result<(), SeaError> {
+        self.push_samples_inner(samples, &mut writer)
+    }
+
+    /// Same as `push_samples`, but takes one slice per channel, all of the same length.
+    #[cfg(feature = "std")]
+    pub fn push_planar_samples<S: InputSample>(
+        &mut self,
+        planes: &[&[S]],
+        mut writer: impl std::io::Write,
+    ) -> Result<(), SeaError> {
+        self.push_planar_samples_inner(planes, &mut writer)
+    }
+
+    #[cfg(not(feature = "std"))]
+    pub fn push_planar_samples<S: InputSample>(
+        &mut self,
+        planes: &[&[S]],
+        mut writer: &mut alloc::vec::Vec<u8>,
+    ) -> Result<(), SeaError> {
+        self.push_planar_samples_inner(planes, &mut writer)
+    }
+
+    /// Writes the remaining buffered frames as a final, possibly shorter chunk.
+    #[cfg(feature = "std")]
+    pub fn finalize(&mut self, mut writer: impl std::io::Write) -> Result<(), SeaError> {
+        self.finalize_inner(&mut writer)
+    }
+
+    #[cfg(not(feature = "std"))]
+    pub fn finalize(&mut self, mut writer: &mut alloc::vec::Vec<u8>) -> Result<(), SeaError> {
+        self.finalize_inner(&mut writer)
+    }
+
+    /// Same as `finalize`, but also rewrites the already written file header with the
+    /// final frame count and chunk size, so the last short chunk becomes decodable.
+    ///
+    /// `writer` must be positioned right after the data written by this encoder.
+    #[cfg(feature = "std")]
+    pub fn finalize_seekable<W: std::io::Write + std::io::Seek>(
+        &mut self,
+        mut writer: W,
+    ) -> Result<(), SeaError> {
+        use std::io::SeekFrom;
+
+        self.finalize_inner(&mut writer)?;
+
+        let end = writer.stream_position()?;
+        let header_start = end
+            .checked_sub(self.written_bytes)
+            .ok_or(SeaError::InvalidParameters)?;
+
+        self.file.header.set_total_frames(self.written_frames)?;
+
+        writer.seek(SeekFrom::Start(header_start))?;
+        writer.write_all(&self.file.header.serialize())?;
+        writer.seek(SeekFrom::Start(end))?;
+
+        Ok(())
+    }
+
+    fn push_samples_inner<S: InputSample, W: InternalWrite>(
+        &mut self,
+        samples: &[S],
+        writer: &mut W,
+    ) -> Result<(), SeaError> {
+        if matches!(self.state, SeaEncoderState::Finished) {
+            return Err(SeaError::EncoderClosed);
+        }
+
+        let channels = self.file.header.channels as usize;
+        let chunk_samples = self.file.header.frames_per_chunk as usize * channels;
+
+        let mut samples = samples;
+        while !samples.is_empty() {
+            let to_copy = (chunk_samples - self.pending.len()).min(samples.len());
+            for sample in &samples[..to_copy] {
+                let channel = self.pending.len() % channels;
+                self.pending
+                    .push(sample.to_sample(&mut self.converter, channel));
+            }
+            samples = &samples[to_copy..];
+
+            if self.pending.len() == chunk_samples {
+                self.write_pending_chunk(writer)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn push_planar_samples_inner<S: InputSample, W: InternalWrite>(
+        &mut self,
+        planes: &[&[S]],
+        writer: &mut W,
+    ) -> Result<(), SeaError> {
+        if matches!(self.state, SeaEncoderState::Finished) {
+            return Err(SeaError::EncoderClosed);
+        }
+
+        let channels = self.file.header.channels as usize;
+        let frames = planes.first().map_or(0, |plane| plane.len());
+        // planar input can only continue on a frame boundary
+        if planes.len() != channels
+            || planes.iter().any(|plane| plane.len() != frames)
+            || !self.pending.len().is_multiple_of(channels)
+        {
+            return Err(SeaError::InvalidParameters);
+        }
+
+        let chunk_samples = self.file.header.frames_per_chunk as usize * channels;
+
+        for frame in 0..frames {
+            for (channel, plane) in planes.iter().enumerate() {
+                self.pending
+                    .push(plane[frame].to_sample(&mut self.converter, channel));
+            }
+
+            if self.pending.len() == chunk_samples {
+                self.write_pending_chunk(writer)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn finalize_inner<W: InternalWrite>(&mut self, writer: &mut W) -> Result<(), SeaError> {
+        if matches!(self.state, SeaEncoderState::Finished) {
+            return Err(SeaError::EncoderClosed);
+        }
+
+        if !self
+            .pending
+            .len()
+            .is_multiple_of(self.file.header.channels as usize)
+        {
+            return Err(SeaError::InvalidParameters);
+        }
+
+        if !self.pending.is_empty() {
+            self.write_pending_chunk(writer)?;
+        } else if matches!(self.state, SeaEncoderState::Start) {
+            let header = self.file.empty_header()?;
+            self.write_output(writer, &header)?;
+        }
+
+        self.state = SeaEncoderState::Finished;
+        Ok(())
+    }
+
+    fn write_pending_chunk<W: InternalWrite>(&mut self, writer: &mut W) -> Result<(), SeaError> {
+        let encoded_chunk = self.file.make_chunk(&self.pending)?;
+
+        // we need to write file header after the first chunk is generated
+        if matches!(self.state, SeaEncoderState::Start) {
+            self.write_output(writer, &self.file.header.serialize())?;
+            self.state = SeaEncoderState::WritingFrames;
+        }
+
+        self.write_output(writer, &encoded_chunk)?;
+        self.written_frames += (self.pending.len() / self.file.header.channels as usize) as u64;
+        self.pending.clear();
+
+        Ok(())
+    }
+
+    fn write_output<W: InternalWrite>(
+        &mut self,
+        writer: &mut W,
+        buf: &[u8],
+    ) -> Result<(), SeaError> {
+        writer.write_all(buf)?;
+        self.written_bytes += buf.len() as u64;
+        Ok(())
+    }
+}
+
+/// Frames encoded ahead of a segment that does not start the file, so its predictors are
+/// adapted to the signal instead of starting from their initial state
+#[cfg(feature = "parallel")]
+const WARM_UP_FRAMES: usize = 1024;
+
+/// Encodes the chunks of a segment, after warming up on the samples before `start`
+#[cfg(feature = "parallel")]
+fn encode_segment(
+    file: &mut SeaFile,
+    samples: &[i32],
+    start: usize,
+    end: usize,
+) -> Result<Vec<u8>, SeaError> {
+    let channels = file.header.channels as usize;
+    let chunk_samples = file.header.frames_per_chunk as usize * channels;
+
+    if start > 0 {
+        let warm_up_start = start.saturating_sub(WARM_UP_FRAMES * channels);
+        file.warm_up(&samples[warm_up_start..start]);
+    }
+
+    let mut output = Vec::new();
+    for chunk in samples[start..end].chunks(chunk_samples) {
+        output.extend_from_slice(&file.make_chunk(chunk)?);
+    }
+    Ok(output)
+}
+
+/// Splits the input at chunk boundaries and encodes the segments on separate threads.
+/// Chunks store the predictor state they start with, so the segments join into one file.
+#[cfg(feature = "parallel")]
+pub(crate) fn encode_parallel<S: InputSample>(
+    input_samples: &[S],
+    channels: u8,
+    sample_rate: u32,
+    settings: EncoderSettings,
+    threads: usize,
+) -> Result<Vec<u8>, SeaError> {
+    settings.validate()?;
+    if channels == 0 {
+        return Err(SeaError::InvalidParameters);
+    }
+
+    let frames = input_samples.len() / channels as usize;
+    let mut converter =
+        SampleConverter::new(channels, settings.sample_depth, settings.float_conversion)?;
+    let mut samples = Vec::new();
+    convert_samples(
+        &input_samples[..frames * channels as usize],
+        &mut converter,
+        channels as usize,
+        &mut samples,
+    );
+
+    let new_file = || {
+        let header = SeaFileHeader::new(channels, sample_rate, frames as u64, &settings);
+        SeaFile::new(header, &settings)
+    };
+    let mut first_file = new_file()?;
+    if frames == 0 {
+        return first_file.empty_header();
+    }
+
+    // every segment starts with a full chunk, the last one also takes the short final chunk,
+    // so all segments derive the same chunk size and file version from their first chunk
+    let full_chunks = (frames / settings.frames_per_chunk as usize).max(1);
+    let threads = match threads {
+        0 => std::thread::available_parallelism().map_or(1, |threads| threads.get()),
+        threads => threads,
+    };
+    let segments = threads.min(full_chunks);
+    let chunk_samples = settings.frames_per_chunk as usize * channels as usize;
+    let segment_start = |segment: usize| segment * full_chunks / segments * chunk_samples;
+    let segment_end = |segment: usize| {
+        if segment + 1 == segments {
+            samples.len()
+        } else {
+            segment_start(segment + 1)
+        }
+    };
+
+    let (first_segment, other_segments) = std::thread::scope(|scope| {
+        let handles: Vec<_> = (1..segments)
+            .map(|segment| {
+                let samples = &samples;
+                scope.spawn(move || {
+                    // the file header shares its metadata through `Rc`, every thread builds its own
+                    encode_segment(
+                        &mut new_file()?,
+                        samples,
+                        segment_start(segment),
+                        segment_end(segment),
+                    )
+                })
+            })
+            .collect();
+
+        let first_segment = encode_segment(&mut first_file, &samples, 0, segment_end(0));
+        let other_segments: Vec<_> = handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect();
+        (first_segment, other_segments)
+    });
+
+    // the header is final once the first chunk is encoded
+    let mut output = first_file.header.serialize();
+    output.extend_from_slice(&first_segment?);
+    for segment in other_segments {
+        output.extend_from_slice(&segment?);
+    }
+    Ok(output)
+}
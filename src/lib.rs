@@ -1,62 +1,140 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
+#[cfg(feature = "alloc")]
 extern crate alloc;
 
+#[cfg(feature = "alloc")]
 use alloc::vec::Vec;
-use decoder::SeaDecoder;
-use encoder::{EncoderSettings, SeaEncoder};
+#[cfg(feature = "alloc")]
+use decoder::{OutputSample, SeaDecoder};
+#[cfg(feature = "alloc")]
+use encoder::{EncoderSettings, InputSample, SeaEncoder};
 
 mod codec;
+#[cfg(feature = "alloc")]
+pub mod convert;
 mod cursor;
+#[cfg(feature = "alloc")]
 pub mod decoder;
+pub mod embedded;
+#[cfg(feature = "alloc")]
 pub mod encoder;
+#[cfg(feature = "alloc")]
+pub mod metadata;
+#[cfg(feature = "alloc")]
 pub mod resample;
+
+pub use codec::common::SeaError;
 #[cfg(all(target_arch = "wasm32", feature = "wasm-api"))]
 pub mod wasm_api;
 
 #[cfg(feature = "c-api")]
 pub mod c_api;
 
-pub fn sea_encode(
-    input_samples: &[i16],
+/// Encodes interleaved samples: `i16`, `i32` at `settings.sample_depth`, or `f32`
+/// converted as set by `settings.float_conversion`.
+///
+/// Returns `SeaError::InvalidSettings` if `settings` do not pass `EncoderSettings::validate`.
+#[cfg(feature = "alloc")]
+pub fn sea_encode<S: InputSample>(
+    input_samples: &[S],
     sample_rate: u32,
     channels: u32,
     settings: EncoderSettings,
-) -> Vec<u8> {
+) -> Result<Vec<u8>, SeaError> {
+    let channels = u8::try_from(channels).map_err(|_| SeaError::InvalidParameters)?;
+    if channels == 0 {
+        return Err(SeaError::InvalidParameters);
+    }
+
     let mut sea_encoded = Vec::<u8>::with_capacity(input_samples.len());
     let mut sea_encoder = SeaEncoder::from_slice(
-        channels as u8,
+        channels,
         sample_rate,
-        Some(input_samples.len() as u32 / channels),
+        Some(input_samples.len() as u64 / channels as u64),
         settings,
         input_samples,
-    )
-    .unwrap();
+    )?;
 
-    while sea_encoder.encode_frame(&mut sea_encoded).unwrap() {}
-    sea_encoder.finalize().unwrap();
+    while sea_encoder.encode_frame(&mut sea_encoded)? {}
+    sea_encoder.finalize()?;
 
-    sea_encoded
+    Ok(sea_encoded)
 }
 
-pub struct SeaDecodeInfo {
-    pub samples: Vec<i16>,
+/// Same as `sea_encode`, but encodes on up to `threads` threads, or one per available core
+/// when `threads` is zero. The input is split at chunk boundaries and every part warms up
+/// its predictors on the preceding samples, so the output differs slightly from `sea_encode`
+/// around the splits.
+#[cfg(feature = "parallel")]
+pub fn sea_encode_parallel<S: InputSample>(
+    input_samples: &[S],
+    sample_rate: u32,
+    channels: u32,
+    settings: EncoderSettings,
+    threads: usize,
+) -> Result<Vec<u8>, SeaError> {
+    let channels = u8::try_from(channels).map_err(|_| SeaError::InvalidParameters)?;
+    encoder::encode_parallel(input_samples, channels, sample_rate, settings, threads)
+}
+
+/// Same as `sea_encode`, but takes one slice per channel.
+///
+/// Returns `SeaError::InvalidParameters` if the slices differ in length.
+#[cfg(feature = "alloc")]
+pub fn sea_encode_planar<S: InputSample>(
+    planes: &[&[S]],
+    sample_rate: u32,
+    settings: EncoderSettings,
+) -> Result<Vec<u8>, SeaError> {
+    let frames = planes.first().map_or(0, |plane| plane.len());
+    if planes.iter().any(|plane| plane.len() != frames) {
+        return Err(SeaError::InvalidParameters);
+    }
+
+    let mut interleaved = Vec::with_capacity(frames * planes.len());
+    for frame in 0..frames {
+        interleaved.extend(planes.iter().map(|plane| plane[frame]));
+    }
+
+    sea_encode(&interleaved, sample_rate, planes.len() as u32, settings)
+}
+
+#[cfg(feature = "alloc")]
+pub struct SeaDecodeInfo<S = i16> {
+    pub samples: Vec<S>,
     pub sample_rate: u32,
     pub channels: u32,
+    /// Bits per sample stored in the file
+    pub sample_depth: u8,
 }
 
-pub fn sea_decode(encoded: &[u8]) -> SeaDecodeInfo {
-    let mut sea_decoded = Vec::<i16>::with_capacity(encoded.len() * 8);
+/// Decodes a file to 16-bit samples, higher resolution files are reduced to 16 bits.
+#[cfg(feature = "alloc")]
+pub fn sea_decode(encoded: &[u8]) -> Result<SeaDecodeInfo, SeaError> {
+    decode_samples(encoded)
+}
+
+/// Decodes a file to `i32` samples at the sample depth of the file.
+#[cfg(feature = "alloc")]
+pub fn sea_decode_i32(encoded: &[u8]) -> Result<SeaDecodeInfo<i32>, SeaError> {
+    decode_samples(encoded)
+}
 
-    let mut sea_decoder = SeaDecoder::from_slice(encoded).unwrap();
+#[cfg(feature = "alloc")]
+fn decode_samples<S: OutputSample>(encoded: &[u8]) -> Result<SeaDecodeInfo<S>, SeaError> {
+    let mut sea_decoded = Vec::<S>::with_capacity(encoded.len() * 8);
 
-    while sea_decoder.decode_frame(&mut sea_decoded).unwrap() {}
+    let mut sea_decoder = SeaDecoder::from_slice(encoded)?;
+
+    while sea_decoder.decode_frame_as(&mut sea_decoded)? {}
 
     let header = sea_decoder.get_header();
 
-    SeaDecodeInfo {
+    Ok(SeaDecodeInfo {
         samples: sea_decoded,
         sample_rate: header.sample_rate,
         channels: header.channels as u32,
-    }
+        sample_depth: header.sample_depth,
+    })
 }
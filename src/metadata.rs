@@ -0,0 +1,111 @@
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::codec::common::SeaError;
+
+/// Largest serialized size of the metadata encoders store, so file headers stay small
+pub const MAX_METADATA_SIZE: usize = 64 * 1024;
+
+/// Key-value metadata stored in the file header.
+///
+/// Keys are compared case-insensitively and cannot contain `=` or `\n`,
+/// values can contain any characters except `\n`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SeaMetadata {
+    entries: Vec<(String, String)>,
+}
+
+fn keys_equal(a: &str, b: &str) -> bool {
+    a.chars()
+        .flat_map(char::to_lowercase)
+        .eq(b.chars().flat_map(char::to_lowercase))
+}
+
+impl SeaMetadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses the serialized `key=value\n` form stored in SEA files. Lines without `=` or
+    /// with an empty key are skipped, a malformed tag does not make the audio undecodable.
+    pub fn parse(text: &str) -> Self {
+        let mut metadata = Self::new();
+
+        for line in text.split('\n') {
+            if let Some((key, value)) = line.split_once('=') {
+                // only fails for empty keys
+                let _ = metadata.insert(key, value);
+            }
+        }
+
+        metadata
+    }
+
+    /// Inserts a pair, replacing the value of an existing key.
+    pub fn insert(&mut self, key: &str, value: &str) -> Result<(), SeaError> {
+        if key.is_empty() || key.contains(['=', '\n']) || value.contains('\n') {
+            return Err(SeaError::InvalidParameters);
+        }
+
+        match self
+            .entries
+            .iter_mut()
+            .find(|(existing, _)| keys_equal(existing, key))
+        {
+            Some((_, existing_value)) => *existing_value = value.to_string(),
+            None => self.entries.push((key.to_string(), value.to_string())),
+        }
+
+        Ok(())
+    }
+
+    /// Builder variant of `insert`.
+    pub fn with(mut self, key: &str, value: &str) -> Result<Self, SeaError> {
+        self.insert(key, value)?;
+        Ok(self)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(existing, _)| keys_equal(existing, key))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let index = self
+            .entries
+            .iter()
+            .position(|(existing, _)| keys_equal(existing, key))?;
+        Some(self.entries.remove(index).1)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut output = String::new();
+        for (index, (key, value)) in self.entries.iter().enumerate() {
+            if index > 0 {
+                output.push('\n');
+            }
+            output.push_str(key);
+            output.push('=');
+            output.push_str(value);
+        }
+        output
+    }
+}
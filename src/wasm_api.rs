@@ -29,6 +29,7 @@ pub extern "C" fn setup() {
     }));
 }
 
+/// Returns the encoded length, or zero when the settings are invalid or the output does not fit
 #[no_mangle]
 pub extern "C" fn wasm_sea_encode(
     input_samples: *const i16,
@@ -45,7 +46,7 @@ pub extern "C" fn wasm_sea_encode(
     use crate::sea_encode;
 
     let input_samples = unsafe { std::slice::from_raw_parts(input_samples, input_length / 2) };
-    let encoded_data = sea_encode(
+    let Ok(encoded_data) = sea_encode(
         input_samples,
         sample_rate,
         channels,
@@ -55,9 +56,13 @@ pub extern "C" fn wasm_sea_encode(
             vbr_residual_beam_width: vbr_effort,
             ..Default::default()
         },
-    );
+    ) else {
+        return 0;
+    };
 
-    assert!(encoded_data.len() <= output_length);
+    if encoded_data.len() > output_length {
+        return 0;
+    }
 
     unsafe {
         std::ptr::copy_nonoverlapping(encoded_data.as_ptr(), output_buffer, encoded_data.len());
@@ -66,6 +71,8 @@ pub extern "C" fn wasm_sea_encode(
     encoded_data.len()
 }
 
+/// Returns the decoded length in bytes, or zero when the file is malformed or the output does
+/// not fit
 #[no_mangle]
 pub extern "C" fn wasm_sea_decode(
     encoded: *const u8,
@@ -78,9 +85,13 @@ pub extern "C" fn wasm_sea_decode(
     use crate::sea_decode;
 
     let encoded_data = unsafe { std::slice::from_raw_parts(encoded, encoded_length) };
-    let decoded_data = sea_decode(encoded_data);
+    let Ok(decoded_data) = sea_decode(encoded_data) else {
+        return 0;
+    };
 
-    assert!(decoded_data.samples.len() * 2 <= output_length);
+    if decoded_data.samples.len() * 2 > output_length {
+        return 0;
+    }
 
     unsafe {
         std::ptr::copy_nonoverlapping(
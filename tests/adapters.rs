@@ -0,0 +1,169 @@
+use std::io::Read;
+
+use helpers::{gen_test_signal, TEST_SAMPLE_RATE};
+use sea_codec::{decoder::SeaDecoder, encoder::EncoderSettings, sea_decode, sea_encode, SeaError};
+
+extern crate sea_codec;
+
+mod helpers;
+
+fn encoded_stereo() -> Vec<u8> {
+    sea_encode(
+        &gen_test_signal(2, 5500),
+        TEST_SAMPLE_RATE,
+        2,
+        EncoderSettings {
+            frames_per_chunk: 1000,
+            vbr: true,
+            ..Default::default()
+        },
+    )
+    .unwrap()
+}
+
+#[test]
+fn samples_iterator() {
+    let encoded = encoded_stereo();
+    let reference = sea_decode(&encoded).unwrap().samples;
+
+    let mut decoder = SeaDecoder::from_slice(&encoded).unwrap();
+    let samples: Result<Vec<i16>, _> = decoder.samples().collect();
+    assert_eq!(samples.unwrap(), reference);
+    assert!(decoder.samples().next().is_none());
+
+    let mut decoder = SeaDecoder::from_slice(&encoded).unwrap();
+    let mut samples = decoder.samples();
+    // the rest of a partially consumed frame comes first
+    assert_eq!(samples.next().unwrap().unwrap(), reference[0]);
+    assert_eq!(samples.next_frame().unwrap().unwrap(), &reference[1..2]);
+
+    let mut frames = 1;
+    while let Some(frame) = samples.next_frame() {
+        assert_eq!(frame.unwrap(), &reference[frames * 2..frames * 2 + 2]);
+        frames += 1;
+    }
+    assert_eq!(frames * 2, reference.len());
+}
+
+#[test]
+fn samples_iterator_stops_after_error() {
+    let mut encoded = encoded_stereo();
+    let damaged_chunk = encoded.len() - 100;
+    encoded[damaged_chunk - 2000..].fill(0xff);
+
+    let mut decoder = SeaDecoder::from_slice(&encoded).unwrap();
+    let mut samples = decoder.samples();
+    assert!(samples.by_ref().any(|sample| sample.is_err()));
+    assert!(samples.next().is_none());
+}
+
+#[test]
+fn pcm_reader() {
+    let encoded = encoded_stereo();
+    let reference: Vec<u8> = sea_decode(&encoded)
+        .unwrap()
+        .samples
+        .iter()
+        .flat_map(|sample| sample.to_le_bytes())
+        .collect();
+
+    let mut pcm = Vec::new();
+    SeaDecoder::from_slice(&encoded)
+        .unwrap()
+        .into_pcm_reader()
+        .read_to_end(&mut pcm)
+        .unwrap();
+    assert_eq!(pcm, reference);
+
+    // odd sized reads split samples
+    let mut reader = SeaDecoder::from_reader(encoded.as_slice())
+        .unwrap()
+        .into_pcm_reader();
+    let mut pcm = Vec::new();
+    let mut buf = [0u8; 7];
+    loop {
+        let bytes_read = reader.read(&mut buf).unwrap();
+        if bytes_read == 0 {
+            break;
+        }
+        pcm.extend_from_slice(&buf[..bytes_read]);
+    }
+    assert_eq!(pcm, reference);
+    assert_eq!(reader.decoder().position() as usize * 4, reference.len());
+}
+
+#[test]
+fn pcm_reader_errors_wrap_the_sea_error() {
+    let encoded = encoded_stereo();
+    // cut into the third chunk
+    let truncated = &encoded[..encoded.len() / 3];
+
+    let mut pcm = Vec::new();
+    let err = SeaDecoder::from_slice(truncated)
+        .unwrap()
+        .into_pcm_reader()
+        .read_to_end(&mut pcm)
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    let source = err.get_ref().unwrap().downcast_ref::<SeaError>().unwrap();
+    assert!(matches!(source, SeaError::InvalidFrame { chunk: 2, .. }));
+    assert_eq!(err.to_string(), source.to_string());
+}
+
+#[test]
+fn float_and_planar_output() {
+    let encoded = encoded_stereo();
+    let reference = sea_decode(&encoded).unwrap().samples;
+
+    let mut decoder = SeaDecoder::from_slice(&encoded).unwrap();
+    let mut output = vec![0f32; decoder.max_chunk_samples()];
+    let mut decoded = Vec::new();
+    loop {
+        let samples = decoder.decode_chunk_into(&mut output).unwrap();
+        if samples == 0 {
+            break;
+        }
+        decoded.extend_from_slice(&output[..samples]);
+    }
+    let expected: Vec<f32> = reference.iter().map(|s| *s as f32 / 32768.0).collect();
+    assert_eq!(decoded, expected);
+    assert!(decoded.iter().all(|s| (-1.0..1.0).contains(s)));
+
+    // planar output starting in the middle of a chunk
+    let mut decoder = SeaDecoder::from_slice(&encoded).unwrap();
+    decoder.seek_to_frame(1500).unwrap();
+    let mut left = vec![0i16; 1000];
+    let mut right = vec![0i16; 1000];
+    let mut decoded = (Vec::new(), Vec::new());
+    loop {
+        let frames = decoder
+            .decode_chunk_planar(&mut [&mut left[..], &mut right[..]])
+            .unwrap();
+        if frames == 0 {
+            break;
+        }
+        decoded.0.extend_from_slice(&left[..frames]);
+        decoded.1.extend_from_slice(&right[..frames]);
+    }
+    let expected: Vec<i16> = reference[3000..].iter().step_by(2).copied().collect();
+    assert_eq!(decoded.0, expected);
+    let expected: Vec<i16> = reference[3001..].iter().step_by(2).copied().collect();
+    assert_eq!(decoded.1, expected);
+
+    let mut decoder = SeaDecoder::from_slice(&encoded).unwrap();
+    let mut left = vec![0f32; 1000];
+    let mut right = vec![0f32; 999];
+    assert!(decoder.decode_chunk_planar(&mut [&mut left[..]]).is_err());
+    assert!(decoder
+        .decode_chunk_planar(&mut [&mut left[..], &mut right[..]])
+        .is_err());
+    let mut right = vec![0f32; 1000];
+    assert_eq!(
+        decoder
+            .decode_chunk_planar(&mut [&mut left[..], &mut right[..]])
+            .unwrap(),
+        1000
+    );
+    assert_eq!(left[10], reference[20] as f32 / 32768.0);
+    assert_eq!(right[10], reference[21] as f32 / 32768.0);
+}
@@ -0,0 +1,113 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use helpers::{gen_test_signal, TEST_SAMPLE_RATE};
+use sea_codec::{decoder::SeaDecoder, encoder::EncoderSettings, sea_decode, sea_encode};
+
+extern crate sea_codec;
+
+mod helpers;
+
+struct CountingAllocator;
+
+thread_local! {
+    // per thread, so tests running in parallel do not affect each other
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn count_allocation() {
+    let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count_allocation();
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count_allocation();
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+// decodes every chunk after the first one, returns the decoded samples and the allocation count
+fn decode_steady_state(mut decoder: SeaDecoder, first_chunk: &mut [i16]) -> (Vec<i16>, usize) {
+    let mut decoded = Vec::with_capacity(1 << 20);
+    let mut output = vec![0i16; decoder.max_chunk_samples()];
+
+    let samples = decoder.decode_chunk_into(first_chunk).unwrap();
+    decoded.extend_from_slice(&first_chunk[..samples]);
+
+    let allocations_before = ALLOCATIONS.with(Cell::get);
+    loop {
+        let samples = decoder.decode_chunk_into(&mut output).unwrap();
+        if samples == 0 {
+            break;
+        }
+        decoded.extend_from_slice(&output[..samples]);
+    }
+    let allocations = ALLOCATIONS.with(Cell::get) - allocations_before;
+
+    (decoded, allocations)
+}
+
+#[test]
+fn steady_state_decoding_does_not_allocate() {
+    for (vbr, checksum, sync) in [
+        (false, false, false),
+        (true, false, false),
+        (true, true, false),
+        (false, true, true),
+    ] {
+        let input = gen_test_signal(2, 20_000);
+        let encoded = sea_encode(
+            &input,
+            TEST_SAMPLE_RATE,
+            2,
+            EncoderSettings {
+                frames_per_chunk: 1000,
+                vbr,
+                checksum,
+                sync,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let reference = sea_decode(&encoded).unwrap().samples;
+
+        let decoder = SeaDecoder::from_slice(&encoded).unwrap();
+        let mut first_chunk = vec![0i16; decoder.max_chunk_samples()];
+        let (decoded, allocations) = decode_steady_state(decoder, &mut first_chunk);
+        assert_eq!(decoded, reference);
+        assert_eq!(allocations, 0);
+
+        let decoder = SeaDecoder::from_reader(encoded.as_slice()).unwrap();
+        let (decoded, allocations) = decode_steady_state(decoder, &mut first_chunk);
+        assert_eq!(decoded, reference);
+        assert_eq!(allocations, 0);
+    }
+}
+
+#[test]
+fn decode_chunk_into_rejects_small_buffers() {
+    let encoded = sea_encode(
+        &gen_test_signal(1, 5000),
+        TEST_SAMPLE_RATE,
+        1,
+        EncoderSettings::default(),
+    )
+    .unwrap();
+    let mut decoder = SeaDecoder::from_slice(&encoded).unwrap();
+    let mut output = vec![0i16; decoder.max_chunk_samples() - 1];
+    assert!(decoder.decode_chunk_into(&mut output).is_err());
+}
@@ -0,0 +1,107 @@
+use helpers::{gen_test_signal, TEST_SAMPLE_RATE};
+use sea_codec::{encoder::EncoderSettings, sea_decode, sea_encode, SeaError};
+
+extern crate sea_codec;
+
+mod helpers;
+
+// bits per second of an encoded file
+fn measured_bitrate(encoded: &[u8], frames: usize) -> f64 {
+    encoded.len() as f64 * 8.0 * TEST_SAMPLE_RATE as f64 / frames as f64
+}
+
+#[test]
+fn target_bitrate_is_met() {
+    let input = gen_test_signal(2, 200_000);
+    let frames = input.len() / 2;
+
+    for (bitrate, settings) in [
+        (160_000, EncoderSettings::default()),
+        (192_000, EncoderSettings::default()),
+        (
+            320_000,
+            EncoderSettings {
+                checksum: true,
+                sync: true,
+                ..Default::default()
+            },
+        ),
+        (
+            150_000,
+            EncoderSettings {
+                vbr_residual_beam_width: 2,
+                ..Default::default()
+            },
+        ),
+    ] {
+        let settings = settings
+            .with_target_bitrate(bitrate, 2, TEST_SAMPLE_RATE)
+            .unwrap();
+        assert!(settings.vbr);
+        settings.validate().unwrap();
+
+        let encoded = sea_encode(&input, TEST_SAMPLE_RATE, 2, settings).unwrap();
+        let measured = measured_bitrate(&encoded, frames);
+        let deviation = (measured - bitrate as f64).abs() / bitrate as f64;
+        assert!(deviation < 0.01, "target={bitrate} measured={measured}");
+        assert_eq!(sea_decode(&encoded).unwrap().samples.len(), input.len());
+    }
+}
+
+#[test]
+fn scale_factor_spacing_follows_bitrate() {
+    let derive = |bitrate, settings: EncoderSettings| {
+        settings
+            .with_target_bitrate(bitrate, 2, TEST_SAMPLE_RATE)
+            .unwrap()
+            .scale_factor_frames
+    };
+
+    assert_eq!(derive(200_000, EncoderSettings::default()), 40);
+    assert_eq!(derive(440_000, EncoderSettings::default()), 20);
+    assert_eq!(derive(600_000, EncoderSettings::default()), 10);
+
+    // the beam search works on periods of up to 20 frames
+    let beam = EncoderSettings {
+        vbr_residual_beam_width: 3,
+        ..Default::default()
+    };
+    assert_eq!(derive(200_000, beam), 20);
+
+    // spacing always divides the chunk
+    let odd_chunk = EncoderSettings {
+        frames_per_chunk: 3000,
+        ..Default::default()
+    };
+    assert_eq!(derive(200_000, odd_chunk), 40);
+    let odd_chunk = EncoderSettings {
+        frames_per_chunk: 2079,
+        ..Default::default()
+    };
+    assert_eq!(derive(200_000, odd_chunk), 33);
+}
+
+#[test]
+fn unreachable_bitrates_are_rejected() {
+    let settings = EncoderSettings::default();
+    for (bitrate, reason) in [
+        (64_000, "is below 1.5 bits per sample"),
+        (1_000_000, "is above 8 bits per sample"),
+    ] {
+        assert!(matches!(
+            settings
+                .clone()
+                .with_target_bitrate(bitrate, 2, TEST_SAMPLE_RATE),
+            Err(SeaError::InvalidSettings { field: "bitrate", reason: r }) if r == reason
+        ));
+    }
+
+    for (channels, sample_rate) in [(0, TEST_SAMPLE_RATE), (2, 0)] {
+        assert!(matches!(
+            settings
+                .clone()
+                .with_target_bitrate(128_000, channels, sample_rate),
+            Err(SeaError::InvalidParameters)
+        ));
+    }
+}
@@ -0,0 +1,92 @@
+use helpers::{encode_decode, gen_test_signal, TEST_SAMPLE_RATE};
+use sea_codec::{
+    embedded::{DequantTables, SeaEmbeddedDecoder},
+    encoder::EncoderSettings,
+};
+
+extern crate sea_codec;
+
+mod helpers;
+
+#[test]
+fn effort_improves_cbr_at_the_same_size() {
+    let input = gen_test_signal(2, 30_000);
+
+    for residual_bits in [1.0, 2.0, 3.0] {
+        let settings = EncoderSettings {
+            residual_bits,
+            ..Default::default()
+        };
+        let fast = encode_decode(&input, TEST_SAMPLE_RATE, 2, settings.clone());
+
+        let mut previous_psnr = fast.psnr;
+        for vbr_residual_beam_width in [2, 4] {
+            let output = encode_decode(
+                &input,
+                TEST_SAMPLE_RATE,
+                2,
+                EncoderSettings {
+                    vbr_residual_beam_width,
+                    ..settings.clone()
+                },
+            );
+            // same header and chunk size, only the residuals differ
+            assert_eq!(output.encoded.len(), fast.encoded.len());
+            assert_eq!(output.encoded[..22], fast.encoded[..22]);
+            assert!(
+                output.psnr < previous_psnr,
+                "bits={residual_bits} width={vbr_residual_beam_width} psnr={}",
+                output.psnr
+            );
+            previous_psnr = output.psnr;
+        }
+        assert!(previous_psnr < fast.psnr - 0.4, "bits={residual_bits}");
+    }
+}
+
+#[test]
+fn cbr_beam_output_decodes_everywhere() {
+    let input = gen_test_signal(2, 12_000);
+
+    for settings in [
+        EncoderSettings::default(),
+        EncoderSettings {
+            lms_order: 16,
+            mid_side: true,
+            ..Default::default()
+        },
+        EncoderSettings {
+            sample_depth: 24,
+            residual_bits: 2.0,
+            ..Default::default()
+        },
+    ] {
+        // the embedded decoder outputs 16-bit samples
+        let embedded = settings.sample_depth == 16;
+        let output = encode_decode(
+            &input,
+            TEST_SAMPLE_RATE,
+            2,
+            EncoderSettings {
+                vbr_residual_beam_width: 3,
+                ..settings
+            },
+        );
+        assert!(output.psnr < -20.0, "psnr={}", output.psnr);
+
+        if embedded {
+            let mut decoder = SeaEmbeddedDecoder::<2>::new(&output.encoded).unwrap();
+            let tables = DequantTables::<16>::new().unwrap();
+            let mut buffer = vec![0i16; decoder.max_chunk_samples()];
+            let mut decoded = Vec::new();
+            loop {
+                let samples = decoder.decode_chunk(&tables, &mut buffer).unwrap();
+                if samples == 0 {
+                    break;
+                }
+                decoded.extend_from_slice(&buffer[..samples]);
+            }
+            assert_eq!(decoded, output.decoded);
+        }
+    }
+}
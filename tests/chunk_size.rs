@@ -0,0 +1,143 @@
+use helpers::{gen_test_signal, TEST_SAMPLE_RATE};
+use sea_codec::{
+    decoder::SeaDecoder,
+    encoder::{EncoderSettings, SeaEncoder, SeaStreamEncoder},
+    sea_decode, sea_encode, SeaError,
+};
+
+extern crate sea_codec;
+
+mod helpers;
+
+const CHANNELS: u32 = 32;
+const VERSION_OFFSET: usize = 4;
+const CHUNK_SIZE_OFFSET: usize = 6;
+
+// the mono test signal at a different level in every channel
+fn gen_multichannel_signal() -> Vec<i16> {
+    gen_test_signal(1, 6_000)
+        .iter()
+        .flat_map(|&s| (0..CHANNELS).map(move |channel| s / (channel as i16 + 1)))
+        .collect()
+}
+
+#[test]
+fn large_chunks_select_version_3() {
+    let input = gen_multichannel_signal();
+    // 32 channels with 8-bit residuals need more than 64 KiB per chunk
+    let settings = EncoderSettings {
+        residual_bits: 8.0,
+        frames_per_chunk: 4000,
+        ..Default::default()
+    };
+    let encoded = sea_encode(&input, TEST_SAMPLE_RATE, CHANNELS, settings.clone()).unwrap();
+
+    assert_eq!(encoded[VERSION_OFFSET], 3);
+    let chunk_size = u32::from_le_bytes(
+        encoded[CHUNK_SIZE_OFFSET..CHUNK_SIZE_OFFSET + 4]
+            .try_into()
+            .unwrap(),
+    );
+    assert!(chunk_size > u16::MAX as u32);
+
+    let decoder = SeaDecoder::from_slice(&encoded).unwrap();
+    assert_eq!(decoder.get_header().chunk_size, chunk_size);
+
+    let decoded = sea_decode(&encoded).unwrap();
+    assert_eq!(decoded.samples.len(), input.len());
+
+    // same quality as with chunks small enough for version 1
+    let small_chunks = sea_encode(
+        &input,
+        TEST_SAMPLE_RATE,
+        CHANNELS,
+        EncoderSettings {
+            frames_per_chunk: 1000,
+            ..settings
+        },
+    )
+    .unwrap();
+    assert_eq!(small_chunks[VERSION_OFFSET], 1);
+    let reference = sea_decode(&small_chunks).unwrap();
+    let quality = helpers::get_audio_quality(&input, &decoded.samples);
+    let reference_quality = helpers::get_audio_quality(&input, &reference.samples);
+    assert!((quality.psnr - reference_quality.psnr).abs() < 0.5);
+}
+
+#[test]
+fn small_chunks_keep_version_1() {
+    let input = gen_test_signal(2, 10_000);
+    let settings = EncoderSettings {
+        residual_bits: 8.0,
+        frames_per_chunk: 4000,
+        ..Default::default()
+    };
+    let encoded = sea_encode(&input, TEST_SAMPLE_RATE, 2, settings).unwrap();
+    assert_eq!(encoded[VERSION_OFFSET], 1);
+}
+
+#[test]
+fn large_chunks_in_stream_encoder() {
+    let input = gen_multichannel_signal();
+    let settings = EncoderSettings {
+        residual_bits: 8.0,
+        frames_per_chunk: 4000,
+        ..Default::default()
+    };
+    let reference = sea_encode(&input, TEST_SAMPLE_RATE, CHANNELS, settings.clone()).unwrap();
+
+    let mut output = std::io::Cursor::new(Vec::new());
+    let mut encoder = SeaStreamEncoder::new(CHANNELS as u8, TEST_SAMPLE_RATE, settings).unwrap();
+    encoder.push_samples(&input, &mut output).unwrap();
+    encoder.finalize_seekable(&mut output).unwrap();
+
+    let encoded = output.into_inner();
+    assert_eq!(encoded[VERSION_OFFSET], 3);
+    assert_eq!(
+        sea_decode(&encoded).unwrap().samples,
+        sea_decode(&reference).unwrap().samples
+    );
+}
+
+#[test]
+fn large_chunks_with_sync_framing_are_rejected() {
+    let input = gen_multichannel_signal();
+    let settings = EncoderSettings {
+        sync: true,
+        residual_bits: 8.0,
+        frames_per_chunk: 4000,
+        ..Default::default()
+    };
+
+    let mut output = Vec::new();
+    let mut encoder = SeaEncoder::from_slice(
+        CHANNELS as u8,
+        TEST_SAMPLE_RATE,
+        Some(input.len() as u64 / CHANNELS as u64),
+        settings,
+        &input,
+    )
+    .unwrap();
+    assert!(matches!(
+        encoder.encode_frame(&mut output),
+        Err(SeaError::InvalidParameters)
+    ));
+    assert!(output.is_empty());
+}
+
+#[test]
+fn oversized_chunk_size_is_rejected() {
+    let input = gen_test_signal(2, 10_000);
+    let settings = EncoderSettings {
+        long_frame_count: true,
+        ..Default::default()
+    };
+    let mut encoded = sea_encode(&input, TEST_SAMPLE_RATE, 2, settings).unwrap();
+    assert_eq!(encoded[VERSION_OFFSET], 3);
+
+    encoded[CHUNK_SIZE_OFFSET..CHUNK_SIZE_OFFSET + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(matches!(
+        SeaDecoder::from_slice(&encoded),
+        Err(SeaError::InvalidFile { .. })
+    ));
+}
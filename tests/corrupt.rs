@@ -0,0 +1,337 @@
+use helpers::{gen_test_signal, TEST_SAMPLE_RATE};
+use sea_codec::{
+    decoder::{CorruptChunkAction, DecoderOptions, SeaDecoder},
+    encoder::EncoderSettings,
+    sea_decode, sea_encode, SeaError,
+};
+
+extern crate sea_codec;
+
+mod helpers;
+
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+fn encoded_samples() -> Vec<Vec<u8>> {
+    let mut files = Vec::new();
+    for (channels, vbr, residual_bits, checksum) in [
+        (1, false, 1.0, false),
+        (2, false, 8.0, false),
+        (2, true, 2.5, false),
+        (2, true, 4.0, true),
+    ] {
+        let input = gen_test_signal(channels, 900);
+        files.push(
+            sea_encode(
+                &input,
+                TEST_SAMPLE_RATE,
+                channels,
+                EncoderSettings {
+                    frames_per_chunk: 400,
+                    residual_bits,
+                    vbr,
+                    checksum,
+                    ..Default::default()
+                },
+            )
+            .unwrap(),
+        );
+    }
+    files
+}
+
+fn decode_all(encoded: &[u8]) {
+    let _ = sea_decode(encoded);
+
+    // keep decoding after errors as well, decoders must not panic in any state
+    if let Ok(mut decoder) = SeaDecoder::from_slice(encoded) {
+        let mut output = Vec::new();
+        for _ in 0..100 {
+            if let Ok(false) = decoder.decode_frame(&mut output) {
+                break;
+            }
+        }
+    }
+}
+
+#[test]
+fn truncated_files_return_errors() {
+    for encoded in encoded_samples() {
+        for len in (0..encoded.len()).step_by(3) {
+            decode_all(&encoded[..len]);
+        }
+    }
+}
+
+#[test]
+fn corrupt_files_do_not_panic() {
+    let mut rng = XorShift(0x9E37_79B9_7F4A_7C15);
+
+    for encoded in encoded_samples() {
+        for _ in 0..500 {
+            let mut corrupted = encoded.clone();
+            for _ in 0..1 + rng.next() % 4 {
+                let index = (rng.next() % corrupted.len() as u64) as usize;
+                corrupted[index] = rng.next() as u8;
+            }
+            decode_all(&corrupted);
+        }
+    }
+}
+
+#[test]
+fn crafted_lms_state_does_not_overflow() {
+    let input: Vec<i32> = gen_test_signal(1, 1000)
+        .iter()
+        .map(|&s| (s as i32) << 8)
+        .collect();
+    let encoded = sea_encode(
+        &input,
+        TEST_SAMPLE_RATE,
+        1,
+        EncoderSettings {
+            sample_depth: 24,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    // 24-bit history is stored with 32 bits, followed by the 16-bit weights of the order 4 LMS
+    let lms_start = 23 + 4;
+    let mut crafted = encoded.clone();
+    crafted[lms_start + 3 * 4..lms_start + 4 * 4].copy_from_slice(&i32::MAX.to_le_bytes());
+    crafted[lms_start + 16 + 3 * 2..lms_start + 16 + 4 * 2]
+        .copy_from_slice(&0x2000i16.to_le_bytes());
+    decode_all(&crafted);
+}
+
+#[test]
+fn invalid_headers_are_rejected() {
+    let input = gen_test_signal(1, 1000);
+    let encoded = sea_encode(&input, TEST_SAMPLE_RATE, 1, EncoderSettings::default()).unwrap();
+
+    let mut bad_magic = encoded.clone();
+    bad_magic[0] = b'x';
+    assert!(sea_decode(&bad_magic).is_err());
+
+    let mut huge_metadata = encoded.clone();
+    huge_metadata[18..22].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(sea_decode(&huge_metadata).is_err());
+
+    let mut invalid_utf8_metadata = encoded[..22].to_vec();
+    invalid_utf8_metadata[18..22].copy_from_slice(&2u32.to_le_bytes());
+    invalid_utf8_metadata.extend_from_slice(&[0xff, 0xfe]);
+    invalid_utf8_metadata.extend_from_slice(&encoded[22..]);
+    // a damaged tag does not make the audio undecodable
+    assert_eq!(
+        sea_decode(&invalid_utf8_metadata).unwrap().samples,
+        sea_decode(&encoded).unwrap().samples
+    );
+}
+
+#[test]
+fn checksum_detects_corrupted_chunks() {
+    let input = gen_test_signal(2, 3000);
+    let settings = EncoderSettings {
+        frames_per_chunk: 1000,
+        ..Default::default()
+    };
+    let plain = sea_encode(&input, TEST_SAMPLE_RATE, 2, settings.clone()).unwrap();
+    let encoded = sea_encode(
+        &input,
+        TEST_SAMPLE_RATE,
+        2,
+        EncoderSettings {
+            checksum: true,
+            ..settings
+        },
+    )
+    .unwrap();
+
+    assert_eq!(plain[4], 1);
+    assert_eq!(encoded[4], 2);
+    assert_eq!(
+        sea_decode(&encoded).unwrap().samples,
+        sea_decode(&plain).unwrap().samples
+    );
+
+    // flip a single residual bit in the second chunk, the version 2 header is 23 bytes long
+    let chunk_size = SeaDecoder::from_slice(&encoded)
+        .unwrap()
+        .get_header()
+        .chunk_size as usize;
+    let mut corrupted = encoded.clone();
+    corrupted[23 + chunk_size + 200] ^= 0x10;
+
+    let mut decoder = SeaDecoder::from_slice(&corrupted).unwrap();
+    let mut output = Vec::new();
+    assert!(decoder.decode_frame(&mut output).unwrap());
+    let err = decoder.decode_frame(&mut output).unwrap_err();
+    assert!(matches!(
+        err,
+        SeaError::ChecksumMismatch { chunk: 1, offset } if offset == (23 + chunk_size) as u64
+    ));
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "checksum mismatch in chunk 1 at byte offset {}",
+            23 + chunk_size
+        )
+    );
+}
+
+#[test]
+fn checksum_is_verified_before_chunk_fields() {
+    let encoded = sea_encode(
+        &gen_test_signal(2, 2000),
+        TEST_SAMPLE_RATE,
+        2,
+        EncoderSettings {
+            frames_per_chunk: 1000,
+            vbr: true,
+            residual_bits: 2.5,
+            checksum: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let chunk_size = SeaDecoder::from_slice(&encoded)
+        .unwrap()
+        .get_header()
+        .chunk_size as usize;
+
+    // the chunk header and the residual sizes are damaged just like the residuals
+    for offset in 23 + chunk_size..23 + 2 * chunk_size {
+        let mut corrupted = encoded.clone();
+        corrupted[offset] ^= 0x01;
+
+        let mut decoder = SeaDecoder::from_slice(&corrupted).unwrap();
+        let mut output = Vec::new();
+        assert!(decoder.decode_frame(&mut output).unwrap());
+        let err = decoder.decode_frame(&mut output).unwrap_err();
+        assert!(
+            matches!(err, SeaError::ChecksumMismatch { chunk: 1, .. }),
+            "offset={offset} {err}"
+        );
+    }
+
+    // the padding of the last chunk is not part of its checksum
+    let mut padded = encoded.clone();
+    padded.resize(encoded.len() + 100, 0);
+    assert_eq!(
+        sea_decode(&padded).unwrap().samples,
+        sea_decode(&encoded).unwrap().samples
+    );
+}
+
+#[test]
+fn corrupt_chunks_are_concealed() {
+    let input = gen_test_signal(2, 3000);
+    let encoded = sea_encode(
+        &input,
+        TEST_SAMPLE_RATE,
+        2,
+        EncoderSettings {
+            frames_per_chunk: 1000,
+            checksum: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let reference = sea_decode(&encoded).unwrap().samples;
+    let total_frames = reference.len() / 2;
+    let chunk_samples = 2000;
+
+    let chunk_size = SeaDecoder::from_slice(&encoded)
+        .unwrap()
+        .get_header()
+        .chunk_size as usize;
+    let mut corrupted = encoded.clone();
+    corrupted[23 + chunk_size + 200] ^= 0x10;
+
+    let decode_with = |action| {
+        let mut decoder =
+            SeaDecoder::from_slice(&corrupted)
+                .unwrap()
+                .with_options(DecoderOptions {
+                    on_corrupt_chunk: action,
+                });
+        let mut output = Vec::new();
+        while decoder.decode_frame(&mut output).unwrap() {}
+        assert_eq!(decoder.corrupt_chunk_count(), 1);
+        assert_eq!(decoder.last_corrupt_chunk(), Some(1));
+        assert_eq!(decoder.position(), total_frames as u64);
+        output
+    };
+
+    let silence = decode_with(CorruptChunkAction::Silence);
+    assert_eq!(silence.len(), reference.len());
+    assert_eq!(silence[..chunk_samples], reference[..chunk_samples]);
+    assert!(silence[chunk_samples..chunk_samples * 2]
+        .iter()
+        .all(|&s| s == 0));
+    assert_eq!(silence[chunk_samples * 2..], reference[chunk_samples * 2..]);
+
+    let repeated = decode_with(CorruptChunkAction::RepeatLast);
+    assert_eq!(repeated.len(), reference.len());
+    assert_eq!(
+        repeated[chunk_samples..chunk_samples * 2],
+        reference[..chunk_samples]
+    );
+    assert_eq!(
+        repeated[chunk_samples * 2..],
+        reference[chunk_samples * 2..]
+    );
+
+    let skipped = decode_with(CorruptChunkAction::Skip);
+    assert_eq!(skipped.len(), reference.len() - chunk_samples);
+    assert_eq!(skipped[..chunk_samples], reference[..chunk_samples]);
+    assert_eq!(skipped[chunk_samples..], reference[chunk_samples * 2..]);
+}
+
+#[test]
+fn each_corrupt_chunk_is_reported_once() {
+    let encoded = sea_encode(
+        &gen_test_signal(1, 5000),
+        TEST_SAMPLE_RATE,
+        1,
+        EncoderSettings {
+            frames_per_chunk: 1000,
+            checksum: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let chunk_size = SeaDecoder::from_slice(&encoded)
+        .unwrap()
+        .get_header()
+        .chunk_size as usize;
+    let mut corrupted = encoded.clone();
+    for chunk in [1, 3] {
+        corrupted[23 + chunk * chunk_size + 200] ^= 0x10;
+    }
+
+    let mut decoder = SeaDecoder::from_slice(&corrupted)
+        .unwrap()
+        .with_options(DecoderOptions {
+            on_corrupt_chunk: CorruptChunkAction::Silence,
+        });
+    let mut output = Vec::new();
+    let mut reported = Vec::new();
+    let mut count = 0;
+    while decoder.decode_frame(&mut output).unwrap() {
+        if decoder.corrupt_chunk_count() != count {
+            count = decoder.corrupt_chunk_count();
+            reported.push(decoder.last_corrupt_chunk().unwrap());
+        }
+    }
+    assert_eq!(reported, [1, 3]);
+}
@@ -0,0 +1,176 @@
+use std::mem::MaybeUninit;
+
+use helpers::{gen_test_signal, TEST_SAMPLE_RATE};
+use sea_codec::{
+    embedded::{DequantTables, SeaEmbeddedDecoder},
+    encoder::EncoderSettings,
+    sea_decode, sea_encode, SeaError,
+};
+
+extern crate sea_codec;
+
+mod helpers;
+
+fn decode_embedded<const CHANNELS: usize, const SCALE_FACTORS: usize>(
+    encoded: &[u8],
+    tables: &DequantTables<SCALE_FACTORS>,
+) -> Vec<i16> {
+    let mut decoder = SeaEmbeddedDecoder::<CHANNELS>::new(encoded).unwrap();
+    let mut output = [0i16; 1000 * 2];
+    let mut decoded = Vec::new();
+    loop {
+        let samples = decoder.decode_chunk(tables, &mut output).unwrap();
+        if samples == 0 {
+            break;
+        }
+        decoded.extend_from_slice(&output[..samples]);
+    }
+    assert_eq!(decoder.position(), decoder.total_frames());
+    decoded
+}
+
+#[test]
+fn embedded_decoder_matches_decoder() {
+    let tables = DequantTables::<16>::new().unwrap();
+
+    for (vbr, residual_bits, checksum, sync) in [
+        (false, 1.0, false, false),
+        (false, 8.0, false, false),
+        (true, 2.5, false, false),
+        (true, 4.5, true, false),
+        (false, 3.0, true, true),
+    ] {
+        let settings = EncoderSettings {
+            frames_per_chunk: 1000,
+            residual_bits,
+            vbr,
+            checksum,
+            sync,
+            ..Default::default()
+        };
+
+        let mono = sea_encode(
+            &gen_test_signal(1, 5000),
+            TEST_SAMPLE_RATE,
+            1,
+            settings.clone(),
+        )
+        .unwrap();
+        assert_eq!(
+            decode_embedded::<1, 16>(&mono, &tables),
+            sea_decode(&mono).unwrap().samples
+        );
+
+        let stereo = sea_encode(&gen_test_signal(2, 5000), TEST_SAMPLE_RATE, 2, settings).unwrap();
+        assert_eq!(
+            decode_embedded::<2, 16>(&stereo, &tables),
+            sea_decode(&stereo).unwrap().samples
+        );
+    }
+}
+
+#[test]
+fn embedded_decoder_checks_parameters() {
+    let encoded = sea_encode(
+        &gen_test_signal(2, 5000),
+        TEST_SAMPLE_RATE,
+        2,
+        EncoderSettings {
+            frames_per_chunk: 1000,
+            scale_factor_bits: 3,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    assert!(matches!(
+        SeaEmbeddedDecoder::<1>::new(&encoded),
+        Err(SeaError::InvalidParameters)
+    ));
+    assert!(DequantTables::<12>::new().is_err());
+
+    let mut decoder = SeaEmbeddedDecoder::<2>::new(&encoded).unwrap();
+    let mut output = [0i16; 2000];
+    assert!(matches!(
+        decoder.decode_chunk(&DequantTables::<16>::new().unwrap(), &mut output),
+        Err(SeaError::TablesMismatch {
+            scale_factor_bits: 3,
+            sample_depth: 16
+        })
+    ));
+    assert!(matches!(
+        decoder.decode_chunk(&DequantTables::<8>::new().unwrap(), &mut output[..100]),
+        Err(SeaError::InvalidParameters)
+    ));
+
+    // the mismatched chunk is decoded again with matching tables
+    assert_eq!(decoder.position(), 0);
+    assert_eq!(
+        decoder
+            .decode_chunk(&DequantTables::<8>::new().unwrap(), &mut output)
+            .unwrap(),
+        2000
+    );
+    assert_eq!(output[..], sea_decode(&encoded).unwrap().samples[..2000]);
+}
+
+#[test]
+fn embedded_decoder_float_and_planar_output() {
+    let encoded = sea_encode(
+        &gen_test_signal(2, 5000),
+        TEST_SAMPLE_RATE,
+        2,
+        EncoderSettings {
+            frames_per_chunk: 1000,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let reference = sea_decode(&encoded).unwrap().samples;
+    let tables = DequantTables::<16>::new().unwrap();
+
+    let mut decoder = SeaEmbeddedDecoder::<2>::new(&encoded).unwrap();
+    let mut output = [0f32; 2000];
+    assert_eq!(decoder.decode_chunk(&tables, &mut output).unwrap(), 2000);
+    assert_eq!(output[11], reference[11] as f32 / 32768.0);
+
+    decoder.rewind();
+    let mut left = [0i16; 1000];
+    let mut right = [0i16; 1000];
+    assert_eq!(
+        decoder
+            .decode_chunk_planar(&tables, &mut [&mut left, &mut right])
+            .unwrap(),
+        1000
+    );
+    assert_eq!(left[5], reference[10]);
+    assert_eq!(right[5], reference[11]);
+}
+
+#[test]
+fn tables_built_in_place_match_returned_tables() {
+    let mut slot = Box::new(MaybeUninit::<DequantTables<32>>::uninit());
+    let tables = DequantTables::init(&mut slot, 24).unwrap();
+    assert!(*tables == DequantTables::<32>::with_sample_depth(24).unwrap());
+
+    tables.fill(16).unwrap();
+    assert_eq!(tables.sample_depth(), 16);
+    assert!(*tables == DequantTables::<32>::new().unwrap());
+
+    assert!(matches!(tables.fill(8), Err(SeaError::InvalidParameters)));
+    let mut slot = MaybeUninit::<DequantTables<3>>::uninit();
+    assert!(matches!(
+        DequantTables::init(&mut slot, 16),
+        Err(SeaError::InvalidParameters)
+    ));
+}
+
+#[cfg(feature = "static-tables")]
+#[test]
+fn static_tables_match_computed_tables() {
+    use sea_codec::embedded::{STATIC_TABLES_3, STATIC_TABLES_4, STATIC_TABLES_5};
+
+    assert!(STATIC_TABLES_3 == DequantTables::<8>::new().unwrap());
+    assert!(STATIC_TABLES_4 == DequantTables::<16>::new().unwrap());
+    assert!(STATIC_TABLES_5 == DequantTables::<32>::new().unwrap());
+}
@@ -0,0 +1,117 @@
+use std::{error::Error, io};
+
+use helpers::{gen_test_signal, TEST_SAMPLE_RATE};
+use sea_codec::{
+    decoder::SeaDecoder,
+    embedded::{DequantTables, SeaEmbeddedDecoder},
+    encoder::{EncoderSettings, SeaStreamEncoder},
+    sea_encode, SeaError,
+};
+
+extern crate sea_codec;
+
+mod helpers;
+
+// version 1 header without metadata
+const HEADER_SIZE: usize = 22;
+
+fn encode_chunks() -> Vec<u8> {
+    let input = gen_test_signal(2, 3000);
+    sea_encode(
+        &input,
+        TEST_SAMPLE_RATE,
+        2,
+        EncoderSettings {
+            frames_per_chunk: 1000,
+            ..Default::default()
+        },
+    )
+    .unwrap()
+}
+
+#[test]
+fn errors_are_displayable() {
+    let err = SeaStreamEncoder::new(
+        2,
+        TEST_SAMPLE_RATE,
+        EncoderSettings {
+            scale_factor_bits: 9,
+            ..Default::default()
+        },
+    )
+    .err()
+    .unwrap();
+    assert_eq!(
+        err.to_string(),
+        "invalid encoder settings: scale_factor_bits must be between 3 and 5"
+    );
+
+    let mut encoded = encode_chunks();
+    encoded[4] = 9;
+    assert_eq!(
+        SeaDecoder::from_slice(&encoded).err().unwrap().to_string(),
+        "unsupported file version 9"
+    );
+
+    encoded[0] = b'x';
+    assert_eq!(
+        SeaDecoder::from_slice(&encoded).err().unwrap().to_string(),
+        "invalid file: not a SEA file"
+    );
+}
+
+struct FailingReader;
+
+impl io::Read for FailingReader {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::PermissionDenied, "denied"))
+    }
+}
+
+#[test]
+fn io_errors_are_chained() {
+    let err = SeaDecoder::from_reader(FailingReader).err().unwrap();
+    assert_eq!(err.to_string(), "I/O error: denied");
+
+    let source = err.source().unwrap().downcast_ref::<io::Error>().unwrap();
+    assert_eq!(source.kind(), io::ErrorKind::PermissionDenied);
+
+    let boxed: Box<dyn Error> = Box::new(SeaError::EndOfFile);
+    assert!(boxed.source().is_none());
+}
+
+#[test]
+fn chunk_errors_carry_position() {
+    let mut encoded = encode_chunks();
+    let chunk_size = SeaDecoder::from_slice(&encoded)
+        .unwrap()
+        .get_header()
+        .chunk_size as usize;
+    // invalid chunk type in the third chunk
+    let offset = HEADER_SIZE + chunk_size * 2;
+    encoded[offset] = 0xff;
+
+    let mut decoder = SeaDecoder::from_slice(&encoded).unwrap();
+    let mut output = Vec::new();
+    assert!(decoder.decode_frame(&mut output).unwrap());
+    assert!(decoder.decode_frame(&mut output).unwrap());
+    let err = decoder.decode_frame(&mut output).unwrap_err();
+    assert!(matches!(
+        err,
+        SeaError::InvalidFrame { chunk: 2, offset: o } if o == offset as u64
+    ));
+    assert_eq!(
+        err.to_string(),
+        format!("invalid chunk 2 at byte offset {}", offset)
+    );
+
+    let mut decoder = SeaEmbeddedDecoder::<2>::new(&encoded).unwrap();
+    let tables = DequantTables::<16>::new().unwrap();
+    let mut output = vec![0i16; decoder.max_chunk_samples()];
+    decoder.decode_chunk(&tables, &mut output).unwrap();
+    decoder.decode_chunk(&tables, &mut output).unwrap();
+    assert!(matches!(
+        decoder.decode_chunk(&tables, &mut output),
+        Err(SeaError::InvalidFrame { chunk: 2, offset: o }) if o == offset as u64
+    ));
+}
@@ -0,0 +1,235 @@
+use helpers::{gen_test_signal, snr, TEST_SAMPLE_RATE};
+use sea_codec::{
+    convert::{FloatConversion, SampleConverter},
+    encoder::{EncoderSettings, SeaStreamEncoder},
+    sea_decode_i32, sea_encode, sea_encode_planar, SeaError,
+};
+
+extern crate sea_codec;
+
+mod helpers;
+
+fn gen_float_signal(channels: u32, frames: usize) -> Vec<f32> {
+    gen_test_signal(channels, frames)
+        .iter()
+        .map(|&s| s as f32 / 32768.0)
+        .collect()
+}
+
+fn deinterleave<T: Copy>(samples: &[T], channels: usize) -> Vec<Vec<T>> {
+    (0..channels)
+        .map(|channel| {
+            samples
+                .iter()
+                .skip(channel)
+                .step_by(channels)
+                .copied()
+                .collect()
+        })
+        .collect()
+}
+
+const CONVERSIONS: [FloatConversion; 3] = [
+    FloatConversion::Round,
+    FloatConversion::TpdfDither,
+    FloatConversion::NoiseShapedDither,
+];
+
+#[test]
+fn rounded_float_input_matches_integer_input() {
+    let input = gen_test_signal(2, 20_000);
+    let input_f32: Vec<f32> = input.iter().map(|&s| s as f32 / 32768.0).collect();
+
+    for sample_depth in [16, 24] {
+        let settings = EncoderSettings {
+            sample_depth,
+            ..Default::default()
+        };
+        assert_eq!(
+            sea_encode(&input_f32, TEST_SAMPLE_RATE, 2, settings.clone()).unwrap(),
+            sea_encode(&input, TEST_SAMPLE_RATE, 2, settings).unwrap()
+        );
+    }
+}
+
+#[test]
+fn dithered_float_input_round_trip() {
+    let input = gen_float_signal(2, 20_000);
+    let reference: Vec<i32> = input
+        .iter()
+        .map(|&s| (s * 8_388_608.0).round() as i32)
+        .collect();
+
+    for conversion in CONVERSIONS {
+        let encoded = sea_encode(
+            &input,
+            TEST_SAMPLE_RATE,
+            2,
+            EncoderSettings {
+                sample_depth: 24,
+                residual_bits: 8.0,
+                float_conversion: conversion,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let decoded = sea_decode_i32(&encoded).unwrap();
+        assert_eq!(decoded.samples.len(), input.len());
+
+        let snr = snr(&reference, &decoded.samples);
+        assert!(snr > 12.0, "{conversion:?} snr={snr}");
+
+        // conversion is deterministic
+        let again = sea_encode(
+            &input,
+            TEST_SAMPLE_RATE,
+            2,
+            EncoderSettings {
+                sample_depth: 24,
+                residual_bits: 8.0,
+                float_conversion: conversion,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(encoded, again);
+    }
+}
+
+#[test]
+fn out_of_range_float_input_is_clamped() {
+    let input = [
+        2.0,
+        -2.0,
+        1.0,
+        -1.0,
+        f32::INFINITY,
+        f32::NEG_INFINITY,
+        f32::NAN,
+    ];
+    let mut output = Vec::new();
+    SampleConverter::new(1, 16, FloatConversion::Round)
+        .unwrap()
+        .convert(&input, &mut output);
+    assert_eq!(output, [32767, -32768, 32767, -32768, 32767, -32768, 0]);
+}
+
+#[test]
+fn dither_spreads_quantization_error() {
+    // constant signal between two integer levels
+    let input = [0.3 / 32768.0; 4096];
+
+    for conversion in CONVERSIONS {
+        let mut output = Vec::new();
+        SampleConverter::new(1, 16, conversion)
+            .unwrap()
+            .convert(&input, &mut output);
+        let mean = output.iter().sum::<i32>() as f32 / output.len() as f32;
+        match conversion {
+            FloatConversion::Round => assert!(output.iter().all(|&s| s == 0)),
+            _ => assert!((mean - 0.3).abs() < 0.05, "{conversion:?} mean={mean}"),
+        }
+    }
+}
+
+#[test]
+fn noise_shaping_moves_error_to_high_frequencies() {
+    let input: Vec<f32> = (0..8192).map(|i| 0.25 * (i as f32 * 0.01).sin()).collect();
+
+    // error energy after a moving sum lowpass
+    let low_frequency_error = |conversion| {
+        let mut output = Vec::new();
+        SampleConverter::new(1, 16, conversion)
+            .unwrap()
+            .convert(&input, &mut output);
+        let error: Vec<f32> = input
+            .iter()
+            .zip(&output)
+            .map(|(&x, &y)| y as f32 - x * 32768.0)
+            .collect();
+        error
+            .windows(8)
+            .map(|window| window.iter().sum::<f32>().powi(2))
+            .sum::<f32>()
+    };
+
+    let tpdf = low_frequency_error(FloatConversion::TpdfDither);
+    let shaped = low_frequency_error(FloatConversion::NoiseShapedDither);
+    assert!(shaped * 2.0 < tpdf, "shaped={shaped} tpdf={tpdf}");
+}
+
+#[test]
+fn planar_input_matches_interleaved() {
+    let input = gen_float_signal(2, 20_000);
+    let planes = deinterleave(&input, 2);
+    let planes: Vec<&[f32]> = planes.iter().map(|plane| plane.as_slice()).collect();
+
+    for float_conversion in CONVERSIONS {
+        let settings = EncoderSettings {
+            float_conversion,
+            ..Default::default()
+        };
+        assert_eq!(
+            sea_encode_planar(&planes, TEST_SAMPLE_RATE, settings.clone()).unwrap(),
+            sea_encode(&input, TEST_SAMPLE_RATE, 2, settings).unwrap()
+        );
+    }
+}
+
+#[test]
+fn stream_encoder_planar_input() {
+    let input = gen_float_signal(2, 12_000);
+    let planes = deinterleave(&input, 2);
+    let settings = EncoderSettings {
+        float_conversion: FloatConversion::NoiseShapedDither,
+        ..Default::default()
+    };
+
+    let mut interleaved = Vec::new();
+    let mut encoder = SeaStreamEncoder::new(2, TEST_SAMPLE_RATE, settings.clone()).unwrap();
+    for block in input.chunks(1000) {
+        encoder.push_samples(block, &mut interleaved).unwrap();
+    }
+    encoder.finalize(&mut interleaved).unwrap();
+
+    let mut planar = Vec::new();
+    let mut encoder = SeaStreamEncoder::new(2, TEST_SAMPLE_RATE, settings.clone()).unwrap();
+    for (left, right) in planes[0].chunks(700).zip(planes[1].chunks(700)) {
+        encoder
+            .push_planar_samples(&[left, right], &mut planar)
+            .unwrap();
+    }
+    encoder.finalize(&mut planar).unwrap();
+
+    assert_eq!(planar, interleaved);
+}
+
+#[test]
+fn planar_input_rejects_mismatched_channels() {
+    let mut output = Vec::new();
+    let mut encoder = SeaStreamEncoder::new(2, TEST_SAMPLE_RATE, Default::default()).unwrap();
+
+    let left = [0.0f32; 10];
+    let right = [0.0f32; 9];
+    assert!(matches!(
+        encoder.push_planar_samples(&[&left[..], &right[..]], &mut output),
+        Err(SeaError::InvalidParameters)
+    ));
+    assert!(matches!(
+        encoder.push_planar_samples(&[&left[..]], &mut output),
+        Err(SeaError::InvalidParameters)
+    ));
+
+    // a partial frame has been pushed
+    encoder.push_samples(&[0.0f32], &mut output).unwrap();
+    assert!(matches!(
+        encoder.push_planar_samples(&[&left[..], &left[..]], &mut output),
+        Err(SeaError::InvalidParameters)
+    ));
+}
+
+#[test]
+fn converter_rejects_invalid_parameters() {
+    assert!(SampleConverter::new(0, 16, FloatConversion::Round).is_err());
+    assert!(SampleConverter::new(1, 18, FloatConversion::Round).is_err());
+}
@@ -0,0 +1,137 @@
+use std::io::Cursor;
+
+use helpers::{gen_test_signal, TEST_SAMPLE_RATE};
+use sea_codec::{
+    decoder::SeaDecoder,
+    embedded::{DequantTables, SeaEmbeddedDecoder},
+    encoder::{EncoderSettings, SeaEncoder, SeaStreamEncoder},
+    sea_decode, sea_encode, SeaError,
+};
+
+extern crate sea_codec;
+
+mod helpers;
+
+const VERSION_OFFSET: usize = 4;
+const TOTAL_FRAMES_OFFSET: usize = 16;
+
+#[test]
+fn frame_count_above_u32_selects_version_3() {
+    let settings = EncoderSettings::default();
+    let frames_per_chunk = settings.frames_per_chunk as usize;
+    // two full chunks of a file claiming more frames than a u32 can hold
+    let mut input = gen_test_signal(2, frames_per_chunk * 2);
+    input.truncate(frames_per_chunk * 2 * 2);
+    let total_frames = u32::MAX as u64 + 1000;
+
+    let mut encoded = Vec::new();
+    let mut encoder =
+        SeaEncoder::from_slice(2, TEST_SAMPLE_RATE, Some(total_frames), settings, &input).unwrap();
+    while encoder.encode_frame(&mut encoded).unwrap() {}
+
+    assert_eq!(encoded[VERSION_OFFSET], 3);
+    assert_eq!(
+        encoded[TOTAL_FRAMES_OFFSET..TOTAL_FRAMES_OFFSET + 8],
+        total_frames.to_le_bytes()
+    );
+
+    let mut decoder = SeaDecoder::from_slice(&encoded).unwrap();
+    assert_eq!(decoder.get_header().total_frames, total_frames);
+    let mut decoded = Vec::new();
+    while decoder.decode_frame(&mut decoded).unwrap() {}
+    assert_eq!(decoded.len(), input.len());
+    assert_eq!(decoder.position(), (frames_per_chunk * 2) as u64);
+
+    // positions past the 32-bit range
+    let frame = u32::MAX as u64 + 10;
+    decoder.seek_to_frame(frame).unwrap();
+    assert_eq!(decoder.position(), frame);
+
+    let decoder = SeaEmbeddedDecoder::<2>::new(&encoded).unwrap();
+    assert_eq!(decoder.total_frames(), total_frames);
+}
+
+#[test]
+fn long_frame_count_round_trip() {
+    let input = gen_test_signal(2, 12_000);
+    let reference =
+        sea_decode(&sea_encode(&input, TEST_SAMPLE_RATE, 2, EncoderSettings::default()).unwrap())
+            .unwrap();
+
+    let settings = EncoderSettings {
+        long_frame_count: true,
+        ..Default::default()
+    };
+    let encoded = sea_encode(&input, TEST_SAMPLE_RATE, 2, settings).unwrap();
+    assert_eq!(encoded[VERSION_OFFSET], 3);
+
+    let decoded = sea_decode(&encoded).unwrap();
+    assert_eq!(decoded.samples, reference.samples);
+
+    let mut decoder = SeaEmbeddedDecoder::<2>::new(&encoded).unwrap();
+    assert_eq!(decoder.total_frames(), input.len() as u64 / 2);
+    let tables = DequantTables::<16>::new().unwrap();
+    let mut output = vec![0i16; decoder.max_chunk_samples()];
+    let mut embedded_decoded = Vec::new();
+    loop {
+        let samples = decoder.decode_chunk(&tables, &mut output).unwrap();
+        if samples == 0 {
+            break;
+        }
+        embedded_decoded.extend_from_slice(&output[..samples]);
+    }
+    assert_eq!(embedded_decoded, reference.samples);
+}
+
+#[test]
+fn stream_encoder_patches_64_bit_frame_count() {
+    let input = gen_test_signal(2, 12_000);
+
+    let mut output = Cursor::new(Vec::new());
+    let settings = EncoderSettings {
+        long_frame_count: true,
+        ..Default::default()
+    };
+    let mut encoder = SeaStreamEncoder::new(2, TEST_SAMPLE_RATE, settings).unwrap();
+    encoder.push_samples(&input, &mut output).unwrap();
+    encoder.finalize_seekable(&mut output).unwrap();
+    assert_eq!(encoder.written_frames(), input.len() as u64 / 2);
+
+    let encoded = output.into_inner();
+    assert_eq!(encoded[VERSION_OFFSET], 3);
+    let decoded = sea_decode(&encoded).unwrap();
+    assert_eq!(decoded.samples.len(), input.len());
+}
+
+#[test]
+fn sync_stream_with_long_frame_count() {
+    let input = gen_test_signal(2, 12_000);
+    let settings = EncoderSettings {
+        sync: true,
+        long_frame_count: true,
+        ..Default::default()
+    };
+    let encoded = sea_encode(&input, TEST_SAMPLE_RATE, 2, settings).unwrap();
+    let reference = sea_decode(&encoded).unwrap();
+
+    // join after the file header
+    let mut decoder = SeaDecoder::from_sync_slice(&encoded[40..]).unwrap();
+    let mut decoded = Vec::new();
+    while decoder.decode_frame(&mut decoded).unwrap() {}
+    assert!(!decoded.is_empty());
+    assert!(reference.samples.ends_with(&decoded));
+}
+
+#[test]
+fn older_headers_reject_64_bit_frame_counts() {
+    let input = gen_test_signal(2, 1000);
+    let encoded = sea_encode(&input, TEST_SAMPLE_RATE, 2, EncoderSettings::default()).unwrap();
+    assert_eq!(encoded[VERSION_OFFSET], 1);
+
+    let mut header = SeaDecoder::from_slice(&encoded).unwrap().get_header();
+    assert!(header.set_total_frames(u32::MAX as u64).is_ok());
+    assert!(matches!(
+        header.set_total_frames(u32::MAX as u64 + 1),
+        Err(SeaError::TooManyFrames)
+    ));
+}
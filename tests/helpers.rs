@@ -1,9 +1,12 @@
+#![allow(dead_code)]
+
 use sea_codec::{encoder::EncoderSettings, sea_decode, sea_encode};
 
 pub struct EncodeDecodeOutput {
     pub encoded: Vec<u8>,
     pub decoded: Vec<i16>,
     pub compression_ratio: f64,
+    pub psnr: f64,
 }
 
 pub fn encode_decode(
@@ -12,15 +15,18 @@ pub fn encode_decode(
     channels: u32,
     settings: EncoderSettings,
 ) -> EncodeDecodeOutput {
-    let encoded = sea_encode(input_samples, sample_rate, channels, settings);
+    let encoded = sea_encode(input_samples, sample_rate, channels, settings).unwrap();
 
     let encoded_len = encoded.len();
-    let decoded = sea_decode(&encoded);
+    let decoded = sea_decode(&encoded).unwrap();
+    assert_eq!(decoded.samples.len(), input_samples.len());
+    let psnr = get_audio_quality(input_samples, &decoded.samples).psnr;
 
     EncodeDecodeOutput {
         encoded,
         decoded: decoded.samples,
         compression_ratio: (input_samples.len() * 2) as f64 / encoded_len as f64,
+        psnr,
     }
 }
 
@@ -32,7 +38,7 @@ fn write_square_wave(signal: &mut [f32], gain: f32, frequency: f32) {
         if (i % period as usize) < (period / 2.0) as usize {
             *s += gain * 1.0;
         } else {
-            *s += gain * -1.0;
+            *s -= gain;
         }
     }
 }
@@ -114,3 +120,15 @@ pub fn get_audio_quality(a: &[i16], b: &[i16]) -> AudioQualityStats {
 
     AudioQualityStats { rms, psnr }
 }
+
+// signal to noise ratio in dB
+pub fn snr(reference: &[i32], decoded: &[i32]) -> f64 {
+    assert_eq!(reference.len(), decoded.len());
+    let signal: f64 = reference.iter().map(|&s| (s as f64).powi(2)).sum();
+    let noise: f64 = reference
+        .iter()
+        .zip(decoded)
+        .map(|(&a, &b)| (a as f64 - b as f64).powi(2))
+        .sum();
+    10.0 * (signal / noise).log10()
+}
@@ -0,0 +1,126 @@
+use helpers::{encode_decode, gen_test_signal, TEST_SAMPLE_RATE};
+use sea_codec::{
+    decoder::SeaDecoder,
+    embedded::{DequantTables, SeaEmbeddedDecoder},
+    encoder::EncoderSettings,
+    sea_decode, sea_encode, SeaError,
+};
+
+extern crate sea_codec;
+
+mod helpers;
+
+const VERSION_OFFSET: usize = 4;
+const FLAGS_OFFSET: usize = 18;
+const FLAGS_LMS_ORDER: u8 = 0x60;
+
+#[test]
+fn lms_order_is_signalled_in_header() {
+    let input = gen_test_signal(2, 10_000);
+    let default_chunk_size = SeaDecoder::from_slice(
+        &sea_encode(&input, TEST_SAMPLE_RATE, 2, EncoderSettings::default()).unwrap(),
+    )
+    .unwrap()
+    .get_header()
+    .chunk_size as i32;
+
+    for (lms_order, code) in [(2, 1), (8, 2), (16, 3)] {
+        let settings = EncoderSettings {
+            lms_order,
+            ..Default::default()
+        };
+        let encoded = sea_encode(&input, TEST_SAMPLE_RATE, 2, settings).unwrap();
+        assert_eq!(encoded[VERSION_OFFSET], 2);
+        assert_eq!(encoded[FLAGS_OFFSET] & FLAGS_LMS_ORDER, code << 5);
+
+        let header = SeaDecoder::from_slice(&encoded).unwrap().get_header();
+        assert_eq!(header.lms_order, lms_order);
+        // two channels with 16-bit history and weights per tap
+        assert_eq!(
+            header.chunk_size as i32 - default_chunk_size,
+            2 * 4 * (lms_order as i32 - 4)
+        );
+
+        let decoded = sea_decode(&encoded).unwrap().samples;
+        assert_eq!(decoded.len(), input.len());
+
+        let mut decoder = SeaEmbeddedDecoder::<2>::new(&encoded).unwrap();
+        let tables = DequantTables::<16>::new().unwrap();
+        let mut output = vec![0i16; decoder.max_chunk_samples()];
+        let mut embedded_decoded = Vec::new();
+        loop {
+            let samples = decoder.decode_chunk(&tables, &mut output).unwrap();
+            if samples == 0 {
+                break;
+            }
+            embedded_decoded.extend_from_slice(&output[..samples]);
+        }
+        assert_eq!(embedded_decoded, decoded);
+    }
+}
+
+#[test]
+fn default_order_keeps_version_1() {
+    let input = gen_test_signal(2, 5000);
+    let settings = EncoderSettings {
+        lms_order: 4,
+        ..Default::default()
+    };
+    let encoded = sea_encode(&input, TEST_SAMPLE_RATE, 2, settings).unwrap();
+    assert_eq!(encoded[VERSION_OFFSET], 1);
+}
+
+#[test]
+fn longer_predictors_improve_tonal_content() {
+    let input = gen_test_signal(2, 30_000);
+    let psnr = |settings| encode_decode(&input, TEST_SAMPLE_RATE, 2, settings).psnr;
+
+    let mut previous_psnr = f64::MAX;
+    for lms_order in [2, 4, 8, 16] {
+        let cbr = EncoderSettings {
+            lms_order,
+            residual_bits: 2.0,
+            ..Default::default()
+        };
+        let cbr_psnr = psnr(cbr.clone());
+        assert!(
+            cbr_psnr < previous_psnr,
+            "order={lms_order} psnr={cbr_psnr}"
+        );
+        previous_psnr = cbr_psnr;
+
+        // the predictors stay stable with every encoder and sample depth
+        for settings in [
+            EncoderSettings {
+                vbr: true,
+                residual_bits: 2.5,
+                vbr_residual_beam_width: 2,
+                ..cbr.clone()
+            },
+            EncoderSettings {
+                sample_depth: 24,
+                ..cbr
+            },
+        ] {
+            let psnr = psnr(settings);
+            assert!(psnr < -20.0, "order={lms_order} psnr={psnr}");
+        }
+    }
+}
+
+#[test]
+fn invalid_orders_are_rejected() {
+    for lms_order in [0, 1, 3, 5, 32] {
+        let settings = EncoderSettings {
+            lms_order,
+            ..Default::default()
+        };
+        assert!(matches!(
+            settings.validate(),
+            Err(SeaError::InvalidSettings {
+                field: "lms_order",
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,150 @@
+use std::io::Read;
+
+use helpers::{gen_test_signal, TEST_SAMPLE_RATE};
+use sea_codec::{
+    decoder::SeaDecoder,
+    encoder::{EncoderSettings, SeaEncoder, SeaStreamEncoder},
+    metadata::{SeaMetadata, MAX_METADATA_SIZE},
+    sea_encode, SeaError,
+};
+
+extern crate sea_codec;
+
+mod helpers;
+
+#[test]
+fn metadata_roundtrip() {
+    let input = gen_test_signal(2, 4000);
+    let metadata = SeaMetadata::new()
+        .with("Author", "John Doe")
+        .unwrap()
+        .with("title", "My Song = Best Song")
+        .unwrap();
+
+    let mut encoded = Vec::new();
+    let mut encoder = SeaEncoder::from_slice(
+        2,
+        TEST_SAMPLE_RATE,
+        Some(input.len() as u64 / 2),
+        EncoderSettings::default(),
+        &input,
+    )
+    .unwrap()
+    .with_metadata(metadata.clone())
+    .unwrap();
+    while encoder.encode_frame(&mut encoded).unwrap() {}
+
+    let mut decoder = SeaDecoder::from_slice(&encoded).unwrap();
+    assert_eq!(decoder.metadata(), &metadata);
+    assert_eq!(decoder.metadata().get("AUTHOR"), Some("John Doe"));
+    assert_eq!(decoder.metadata().get("Title"), Some("My Song = Best Song"));
+    assert_eq!(decoder.metadata().get("album"), None);
+
+    let mut decoded = Vec::new();
+    while decoder.decode_frame(&mut decoded).unwrap() {}
+    assert_eq!(decoded.len(), input.len());
+}
+
+#[test]
+fn metadata_keys_are_case_insensitive_and_validated() {
+    let mut metadata = SeaMetadata::parse("title=First\nARTIST=Someone\n");
+    metadata.insert("Title", "Second").unwrap();
+    assert_eq!(metadata.len(), 2);
+    assert_eq!(metadata.get("TITLE"), Some("Second"));
+    assert_eq!(metadata.serialize(), "title=Second\nARTIST=Someone");
+
+    assert!(metadata.insert("", "value").is_err());
+    assert!(metadata.insert("a=b", "value").is_err());
+    assert!(metadata.insert("key", "multi\nline").is_err());
+}
+
+#[test]
+fn malformed_metadata_lines_are_skipped() {
+    let metadata = SeaMetadata::parse("missing separator\n=no key\nartist=Someone");
+    assert_eq!(metadata.serialize(), "artist=Someone");
+
+    let input = gen_test_signal(1, 1000);
+    let metadata = SeaMetadata::new()
+        .with("title", "Song")
+        .unwrap()
+        .with("artist", "Someone")
+        .unwrap();
+    let mut encoded = Vec::new();
+    let mut encoder = SeaEncoder::from_slice(
+        1,
+        TEST_SAMPLE_RATE,
+        Some(1000),
+        EncoderSettings::default(),
+        &input,
+    )
+    .unwrap()
+    .with_metadata(metadata)
+    .unwrap();
+    while encoder.encode_frame(&mut encoded).unwrap() {}
+
+    // damage the title tag: drop its separator and break its UTF-8
+    let title = encoded
+        .windows(10)
+        .position(|window| window == b"title=Song")
+        .unwrap();
+    encoded[title + 5] = b' ';
+    encoded[title + 6] = 0xff;
+
+    let mut decoder = SeaDecoder::from_slice(&encoded).unwrap();
+    assert_eq!(decoder.metadata().get("title"), None);
+    assert_eq!(decoder.metadata().get("artist"), Some("Someone"));
+    let mut decoded = Vec::new();
+    while decoder.decode_frame(&mut decoded).unwrap() {}
+    assert_eq!(decoded.len(), input.len());
+}
+
+#[test]
+fn oversized_metadata_is_rejected() {
+    let input = gen_test_signal(1, 1000);
+    let fits = SeaMetadata::new()
+        .with("comment", &"x".repeat(MAX_METADATA_SIZE - "comment=".len()))
+        .unwrap();
+    let too_large = SeaMetadata::new()
+        .with("comment", &"x".repeat(MAX_METADATA_SIZE))
+        .unwrap();
+
+    let encoder = || {
+        SeaEncoder::from_slice(
+            1,
+            TEST_SAMPLE_RATE,
+            Some(1000),
+            EncoderSettings::default(),
+            &input,
+        )
+        .unwrap()
+    };
+    assert!(encoder().with_metadata(fits.clone()).is_ok());
+    assert!(matches!(
+        encoder().with_metadata(too_large.clone()),
+        Err(SeaError::MetadataTooLarge)
+    ));
+
+    let stream_encoder =
+        || SeaStreamEncoder::new(1, TEST_SAMPLE_RATE, EncoderSettings::default()).unwrap();
+    assert!(stream_encoder().with_metadata(fits).is_ok());
+    assert!(matches!(
+        stream_encoder().with_metadata(too_large),
+        Err(SeaError::MetadataTooLarge)
+    ));
+}
+
+#[test]
+fn decoders_reject_oversized_metadata() {
+    let input = gen_test_signal(1, 1000);
+    let encoded = sea_encode(&input, TEST_SAMPLE_RATE, 1, EncoderSettings::default()).unwrap();
+
+    // the version 1 header stores the metadata size at bytes 18..22, an endless stream
+    // follows it
+    let mut header = encoded[..22].to_vec();
+    header[18..22].copy_from_slice(&u32::MAX.to_le_bytes());
+    let hostile = header.as_slice().chain(std::io::repeat(b'x'));
+    assert!(matches!(
+        SeaDecoder::from_reader(hostile),
+        Err(SeaError::MetadataTooLarge)
+    ));
+}
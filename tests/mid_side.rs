@@ -0,0 +1,194 @@
+use helpers::{encode_decode, gen_test_signal, TEST_SAMPLE_RATE};
+use sea_codec::{
+    decoder::SeaDecoder,
+    embedded::{DequantTables, SeaEmbeddedDecoder},
+    encoder::{EncoderSettings, QualityTarget},
+    sea_decode, sea_encode, SeaError,
+};
+
+extern crate sea_codec;
+
+mod helpers;
+
+const VERSION_OFFSET: usize = 4;
+// version 2 header without metadata
+const HEADER_SIZE: usize = 23;
+const CHUNK_FLAGS_OFFSET: usize = 3;
+const CHUNK_FLAG_MID_SIDE: u8 = 0x01;
+
+// a noisy component shared by both channels and a quiet tone in their difference
+fn gen_correlated(frames: usize) -> Vec<i16> {
+    let mut seed = 1u32;
+    let mut common = 0.0;
+    let mut samples = Vec::with_capacity(frames * 2);
+    for frame in 0..frames {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        let noise = ((seed >> 16) % 2000) as f64 - 1000.0;
+        common = common * 0.9 + noise * 4.0;
+        let difference = 1500.0 * (frame as f64 * 0.013).sin();
+        samples.push((common + difference) as i16);
+        samples.push((common - difference) as i16);
+    }
+    samples
+}
+
+// unrelated tones, the left channel much louder
+fn gen_panned(frames: usize) -> Vec<i16> {
+    let mut samples = Vec::with_capacity(frames * 2);
+    for frame in 0..frames {
+        let t = frame as f64;
+        samples.push((12000.0 * (t * 0.031).sin()) as i16);
+        samples.push((1000.0 * (t * 0.187).sin()) as i16);
+    }
+    samples
+}
+
+// mid/side flag of every chunk of a file with fixed chunk sizes
+fn chunk_modes(encoded: &[u8]) -> Vec<bool> {
+    let chunk_size = SeaDecoder::from_slice(encoded)
+        .unwrap()
+        .get_header()
+        .chunk_size as usize;
+    encoded[HEADER_SIZE..]
+        .chunks(chunk_size)
+        .map(|chunk| chunk[CHUNK_FLAGS_OFFSET] & CHUNK_FLAG_MID_SIDE != 0)
+        .collect()
+}
+
+#[test]
+fn mid_side_improves_correlated_stereo() {
+    let input = gen_correlated(20_000);
+
+    // VBR moves the bits saved on the side channel to the mid channel
+    let vbr = EncoderSettings {
+        residual_bits: 2.5,
+        vbr: true,
+        frames_per_chunk: 2000,
+        ..Default::default()
+    };
+    let mid_side = EncoderSettings {
+        mid_side: true,
+        ..vbr.clone()
+    };
+    let encoded = encode_decode(&input, TEST_SAMPLE_RATE, 2, mid_side);
+    let independent = encode_decode(&input, TEST_SAMPLE_RATE, 2, vbr);
+    assert_eq!(encoded.encoded[VERSION_OFFSET], 2);
+    assert!(chunk_modes(&encoded.encoded)
+        .iter()
+        .all(|&mid_side| mid_side));
+    // the version 2 header adds the flags byte
+    assert_eq!(encoded.encoded.len(), independent.encoded.len() + 1);
+    assert!(
+        encoded.psnr < independent.psnr - 0.5,
+        "{} {}",
+        encoded.psnr,
+        independent.psnr
+    );
+
+    // quality targets need fewer bits for the same quality
+    let quality = EncoderSettings {
+        quality_target: Some(QualityTarget::Psnr(70.0)),
+        frames_per_chunk: 2000,
+        ..Default::default()
+    };
+    let mid_side = EncoderSettings {
+        mid_side: true,
+        ..quality.clone()
+    };
+    let encoded = encode_decode(&input, TEST_SAMPLE_RATE, 2, mid_side);
+    let independent = encode_decode(&input, TEST_SAMPLE_RATE, 2, quality);
+    assert!(encoded.encoded.len() * 10 < independent.encoded.len() * 9);
+    assert!(
+        (encoded.psnr - independent.psnr).abs() < 0.5,
+        "{} {}",
+        encoded.psnr,
+        independent.psnr
+    );
+}
+
+#[test]
+fn modes_switch_between_chunks() {
+    let correlated = gen_correlated(4000);
+    let mut input = correlated.clone();
+    input.extend_from_slice(&gen_panned(4000));
+    input.extend_from_slice(&correlated);
+
+    let settings = EncoderSettings {
+        mid_side: true,
+        frames_per_chunk: 2000,
+        ..Default::default()
+    };
+    let encoded = encode_decode(&input, TEST_SAMPLE_RATE, 2, settings.clone());
+    assert_eq!(
+        chunk_modes(&encoded.encoded),
+        [true, true, false, false, true, true]
+    );
+    // the predictors continue across mode changes
+    let independent = EncoderSettings {
+        mid_side: false,
+        ..settings
+    };
+    let independent = encode_decode(&input, TEST_SAMPLE_RATE, 2, independent);
+    assert!(
+        encoded.psnr < independent.psnr + 0.5,
+        "{} {}",
+        encoded.psnr,
+        independent.psnr
+    );
+
+    let mut decoder = SeaEmbeddedDecoder::<2>::new(&encoded.encoded).unwrap();
+    let tables = DequantTables::<16>::new().unwrap();
+    let mut output = vec![0i16; decoder.max_chunk_samples()];
+    let mut embedded_decoded = Vec::new();
+    loop {
+        let samples = decoder.decode_chunk(&tables, &mut output).unwrap();
+        if samples == 0 {
+            break;
+        }
+        embedded_decoded.extend_from_slice(&output[..samples]);
+    }
+    assert_eq!(embedded_decoded, encoded.decoded);
+}
+
+#[test]
+fn mono_ignores_mid_side() {
+    let input = gen_test_signal(1, 5000);
+    let reference = sea_encode(&input, TEST_SAMPLE_RATE, 1, EncoderSettings::default()).unwrap();
+    let encoded = sea_encode(
+        &input,
+        TEST_SAMPLE_RATE,
+        1,
+        EncoderSettings {
+            mid_side: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(encoded, reference);
+}
+
+#[test]
+fn unknown_chunk_flags_are_rejected() {
+    let input = gen_test_signal(1, 5000);
+    let mut encoded = sea_encode(
+        &input,
+        TEST_SAMPLE_RATE,
+        1,
+        EncoderSettings {
+            // stores the chunk flags without a checksum covering them
+            lms_order: 8,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(encoded[VERSION_OFFSET], 2);
+
+    // mono chunks have no side channel
+    for flags in [CHUNK_FLAG_MID_SIDE, 0x02] {
+        encoded[HEADER_SIZE + CHUNK_FLAGS_OFFSET] = flags;
+        assert!(matches!(
+            sea_decode(&encoded),
+            Err(SeaError::InvalidFrame { chunk: 0, .. })
+        ));
+    }
+}
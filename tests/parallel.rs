@@ -0,0 +1,131 @@
+#![cfg(feature = "parallel")]
+
+use helpers::{gen_test_signal, get_audio_quality, TEST_SAMPLE_RATE};
+use sea_codec::{
+    encoder::{EncoderSettings, QualityTarget},
+    sea_decode, sea_encode, sea_encode_parallel, SeaError,
+};
+
+extern crate sea_codec;
+
+mod helpers;
+
+fn test_settings() -> [EncoderSettings; 5] {
+    let base = EncoderSettings {
+        frames_per_chunk: 1000,
+        ..Default::default()
+    };
+    [
+        base.clone(),
+        EncoderSettings {
+            vbr: true,
+            residual_bits: 2.5,
+            vbr_residual_beam_width: 4,
+            ..base.clone()
+        },
+        EncoderSettings {
+            quality_target: Some(QualityTarget::Snr(30.0)),
+            sync: true,
+            ..base.clone()
+        },
+        EncoderSettings {
+            mid_side: true,
+            lms_order: 8,
+            checksum: true,
+            ..base.clone()
+        },
+        EncoderSettings {
+            sample_depth: 24,
+            ..base
+        },
+    ]
+}
+
+#[test]
+fn one_thread_matches_sequential_encoding() {
+    let input = gen_test_signal(2, 10_500);
+    for settings in test_settings() {
+        let encoded = sea_encode_parallel(&input, TEST_SAMPLE_RATE, 2, settings.clone(), 1);
+        let reference = sea_encode(&input, TEST_SAMPLE_RATE, 2, settings).unwrap();
+        assert_eq!(encoded.unwrap(), reference);
+    }
+}
+
+#[test]
+fn segments_join_into_a_valid_file() {
+    let full_scale = gen_test_signal(2, 10_500);
+    // at half scale the side of the delayed channels fits the sample range, so the mid/side
+    // settings switch modes
+    let half_scale: Vec<i16> = full_scale.iter().map(|s| s / 2).collect();
+    for settings in test_settings() {
+        let input = if settings.mid_side {
+            &half_scale
+        } else {
+            &full_scale
+        };
+        let reference = sea_encode(input, TEST_SAMPLE_RATE, 2, settings.clone()).unwrap();
+        let reference_psnr =
+            get_audio_quality(input, &sea_decode(&reference).unwrap().samples).psnr;
+
+        for threads in [2, 3, 4, 20] {
+            let encoded =
+                sea_encode_parallel(input, TEST_SAMPLE_RATE, 2, settings.clone(), threads).unwrap();
+            // the header and the first segment do not change
+            assert_eq!(encoded[..1000], reference[..1000]);
+
+            let decoded = sea_decode(&encoded).unwrap().samples;
+            assert_eq!(decoded.len(), input.len());
+            if settings.quality_target.is_some() {
+                // every period meets the target, the size follows the predictors
+                assert!(
+                    encoded.len() * 20 < reference.len() * 21,
+                    "threads={threads}"
+                );
+            } else {
+                assert_eq!(encoded.len(), reference.len());
+                // every segment restarts its predictors, which costs most with long filters
+                let psnr = get_audio_quality(input, &decoded).psnr;
+                assert!(
+                    (psnr - reference_psnr).abs() < 1.5,
+                    "threads={threads} {psnr} {reference_psnr}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn short_inputs_use_one_segment() {
+    let settings = EncoderSettings {
+        frames_per_chunk: 1000,
+        ..Default::default()
+    };
+    for frames in [0, 1, 999, 1000, 1001] {
+        let input = gen_test_signal(1, frames);
+        let encoded = sea_encode_parallel(&input, TEST_SAMPLE_RATE, 1, settings.clone(), 0);
+        let reference = sea_encode(&input, TEST_SAMPLE_RATE, 1, settings.clone()).unwrap();
+        assert_eq!(encoded.unwrap(), reference, "frames={frames}");
+    }
+}
+
+#[test]
+fn invalid_parameters_are_rejected() {
+    let input = gen_test_signal(2, 1000);
+    let settings = EncoderSettings {
+        scale_factor_bits: 2,
+        ..Default::default()
+    };
+    assert!(matches!(
+        sea_encode_parallel(&input, TEST_SAMPLE_RATE, 2, settings, 2),
+        Err(SeaError::InvalidSettings {
+            field: "scale_factor_bits",
+            ..
+        })
+    ));
+    for channels in [0, 256] {
+        assert!(matches!(
+            sea_encode_parallel(&input, TEST_SAMPLE_RATE, channels, Default::default(), 2),
+            Err(SeaError::InvalidParameters)
+        ));
+    }
+}
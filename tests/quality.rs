@@ -0,0 +1,228 @@
+use std::io::Cursor;
+
+use helpers::{encode_decode, TEST_SAMPLE_RATE};
+use sea_codec::{
+    decoder::{CorruptChunkAction, DecoderOptions, SeaDecoder},
+    embedded::{DequantTables, SeaEmbeddedDecoder},
+    encoder::{EncoderSettings, QualityTarget, SeaStreamEncoder},
+    sea_decode, SeaError,
+};
+
+extern crate sea_codec;
+
+mod helpers;
+
+const VERSION_OFFSET: usize = 4;
+const FLAGS_OFFSET: usize = 18;
+const FLAG_VARIABLE_CHUNKS: u8 = 0x10;
+// version 2 header without metadata
+const HEADER_SIZE: usize = 23;
+const PERIOD_FRAMES: usize = 20;
+
+// tones under a slow envelope, with passages close to silence
+fn gen_tones(channels: usize, frames: usize, gain: f64) -> Vec<i16> {
+    let mut samples = Vec::with_capacity(frames * channels);
+    for frame in 0..frames {
+        let t = frame as f64;
+        let envelope = 0.01 + 0.49 * (0.5 + 0.5 * (t * 0.0002).sin());
+        for channel in 0..channels {
+            let tone = 0.7 * (t * 0.0627).sin() + 0.3 * (t * 0.176 + channel as f64).sin();
+            samples.push((gain * envelope * 32767.0 * tone) as i16);
+        }
+    }
+    samples
+}
+
+#[test]
+fn snr_target_is_met_in_every_period() {
+    let input = gen_tones(2, 30_000, 1.0);
+
+    let mut previous_size = 0;
+    for snr in [20.0, 30.0, 40.0] {
+        let settings = EncoderSettings {
+            quality_target: Some(QualityTarget::Snr(snr)),
+            ..Default::default()
+        };
+        let output = encode_decode(&input, TEST_SAMPLE_RATE, 2, settings);
+        let (encoded, decoded) = (output.encoded, output.decoded);
+
+        for (reference, decoded) in input
+            .chunks(PERIOD_FRAMES * 2)
+            .zip(decoded.chunks(PERIOD_FRAMES * 2))
+        {
+            for channel in 0..2 {
+                let mut signal = 0.0;
+                let mut noise = 0.0;
+                for (&a, &b) in reference.iter().zip(decoded).skip(channel).step_by(2) {
+                    signal += (a as f64).powi(2);
+                    noise += (a as f64 - b as f64).powi(2);
+                }
+                // quantization to whole samples limits near silent periods
+                let allowed = (signal / 10f64.powf(snr as f64 / 10.0)).max(PERIOD_FRAMES as f64);
+                assert!(noise <= allowed, "target={snr} noise={noise}");
+            }
+        }
+
+        assert!(encoded.len() > previous_size);
+        previous_size = encoded.len();
+    }
+}
+
+#[test]
+fn psnr_target_spends_fewer_bits_on_quiet_input() {
+    let loud = gen_tones(2, 30_000, 1.0);
+    let quiet = gen_tones(2, 30_000, 0.05);
+
+    let sizes = |target| {
+        let settings = EncoderSettings {
+            quality_target: Some(target),
+            ..Default::default()
+        };
+        let loud_size = encode_decode(&loud, TEST_SAMPLE_RATE, 2, settings.clone())
+            .encoded
+            .len();
+        let quiet_size = encode_decode(&quiet, TEST_SAMPLE_RATE, 2, settings)
+            .encoded
+            .len();
+        (loud_size, quiet_size)
+    };
+
+    let (loud_size, quiet_size) = sizes(QualityTarget::Psnr(60.0));
+    assert!(quiet_size * 3 < loud_size * 2, "{quiet_size} {loud_size}");
+
+    // SNR is relative to the signal, quiet input keeps most of its size
+    let (snr_loud_size, snr_quiet_size) = sizes(QualityTarget::Snr(30.0));
+    assert!(snr_quiet_size * loud_size > quiet_size * snr_loud_size * 2);
+}
+
+#[test]
+fn variable_chunks_decode_everywhere() {
+    let input = gen_tones(2, 20_000, 1.0);
+    let settings = EncoderSettings {
+        frames_per_chunk: 2000,
+        quality_target: Some(QualityTarget::Snr(30.0)),
+        ..Default::default()
+    };
+    let output = encode_decode(&input, TEST_SAMPLE_RATE, 2, settings);
+    let (encoded, reference) = (output.encoded, output.decoded);
+    assert_eq!(encoded[VERSION_OFFSET], 2);
+    assert_eq!(
+        encoded[FLAGS_OFFSET] & FLAG_VARIABLE_CHUNKS,
+        FLAG_VARIABLE_CHUNKS
+    );
+
+    let mut decoder = SeaDecoder::from_reader(Cursor::new(&encoded)).unwrap();
+    let mut decoded = Vec::new();
+    while decoder.decode_frame(&mut decoded).unwrap() {}
+    assert_eq!(decoded, reference);
+
+    let mut decoder = SeaEmbeddedDecoder::<2>::new(&encoded).unwrap();
+    let tables = DequantTables::<16>::new().unwrap();
+    let mut output = vec![0i16; decoder.max_chunk_samples()];
+    let mut decoded = Vec::new();
+    loop {
+        let samples = decoder.decode_chunk(&tables, &mut output).unwrap();
+        if samples == 0 {
+            break;
+        }
+        decoded.extend_from_slice(&output[..samples]);
+    }
+    assert_eq!(decoded, reference);
+
+    let mut decoder = SeaDecoder::from_slice(&encoded).unwrap();
+    assert!(matches!(
+        decoder.seek_to_frame(5000),
+        Err(SeaError::SeekNotSupported)
+    ));
+}
+
+#[test]
+fn sync_stream_with_variable_chunks() {
+    let input = gen_tones(2, 20_000, 1.0);
+    let settings = EncoderSettings {
+        frames_per_chunk: 2000,
+        sync: true,
+        quality_target: Some(QualityTarget::Snr(30.0)),
+        ..Default::default()
+    };
+
+    let mut encoded = Vec::new();
+    let mut encoder = SeaStreamEncoder::new(2, TEST_SAMPLE_RATE, settings).unwrap();
+    for samples in input.chunks(3000) {
+        encoder.push_samples(samples, &mut encoded).unwrap();
+    }
+    encoder.finalize(&mut encoded).unwrap();
+    let reference = sea_decode(&encoded).unwrap().samples;
+    assert_eq!(reference.len(), input.len());
+
+    // join in the middle of the first chunk
+    let mut decoder = SeaDecoder::from_sync_slice(&encoded[HEADER_SIZE + 100..]).unwrap();
+    let mut decoded = Vec::new();
+    while decoder.decode_frame(&mut decoded).unwrap() {}
+    assert_eq!(decoded.len(), input.len() - 2000 * 2);
+    assert!(reference.ends_with(&decoded));
+}
+
+#[test]
+fn corrupt_variable_chunks_need_sync_to_recover() {
+    let input = gen_tones(2, 6000, 1.0);
+    let decode_corrupted = |sync| {
+        let settings = EncoderSettings {
+            frames_per_chunk: 2000,
+            checksum: true,
+            sync,
+            quality_target: Some(QualityTarget::Snr(30.0)),
+            ..Default::default()
+        };
+        let output = encode_decode(&input, TEST_SAMPLE_RATE, 2, settings);
+        let (mut encoded, reference) = (output.encoded, output.decoded);
+        // residuals of the first chunk
+        encoded[HEADER_SIZE + 400] ^= 0x10;
+
+        let mut decoder = SeaDecoder::from_slice(&encoded)
+            .unwrap()
+            .with_options(DecoderOptions {
+                on_corrupt_chunk: CorruptChunkAction::Skip,
+            });
+        let mut decoded = Vec::new();
+        loop {
+            match decoder.decode_frame(&mut decoded) {
+                Ok(true) => {}
+                Ok(false) => return Ok((reference, decoded)),
+                Err(err) => return Err(err),
+            }
+        }
+    };
+
+    assert!(matches!(
+        decode_corrupted(false),
+        Err(SeaError::ChecksumMismatch { chunk: 0, .. })
+    ));
+
+    let (reference, decoded) = decode_corrupted(true).unwrap();
+    assert_eq!(decoded, reference[2000 * 2..]);
+}
+
+#[test]
+fn quality_target_is_validated() {
+    for db in [0.0, 250.0, f32::NAN] {
+        for target in [QualityTarget::Snr(db), QualityTarget::Psnr(db)] {
+            let settings = EncoderSettings {
+                quality_target: Some(target),
+                ..Default::default()
+            };
+            assert!(matches!(
+                settings.validate(),
+                Err(SeaError::InvalidSettings {
+                    field: "quality_target",
+                    ..
+                })
+            ));
+        }
+    }
+    let settings = EncoderSettings {
+        quality_target: Some(QualityTarget::Psnr(96.0)),
+        ..Default::default()
+    };
+    settings.validate().unwrap();
+}
@@ -0,0 +1,216 @@
+use helpers::{gen_test_signal, snr, TEST_SAMPLE_RATE};
+use sea_codec::{
+    decoder::SeaDecoder,
+    embedded::{DequantTables, SeaEmbeddedDecoder},
+    encoder::{EncoderSettings, SeaEncoder},
+    sea_decode, sea_decode_i32, sea_encode, SeaError,
+};
+
+extern crate sea_codec;
+
+mod helpers;
+
+// test signal with content below the 16-bit resolution
+fn gen_high_res_signal(channels: u32, frames: usize, sample_depth: u8) -> Vec<i32> {
+    let shift = sample_depth - 16;
+    gen_test_signal(channels, frames)
+        .iter()
+        .enumerate()
+        .map(|(index, &s)| ((s as i32) << shift) + ((index as i32 * 7) % (1 << shift)))
+        .collect()
+}
+
+#[test]
+fn high_resolution_round_trip() {
+    for sample_depth in [20, 24] {
+        for (vbr, residual_bits) in [(false, 3.0), (false, 8.0), (true, 4.5)] {
+            let input = gen_high_res_signal(2, 20_000, sample_depth);
+            let settings = EncoderSettings {
+                sample_depth,
+                residual_bits,
+                vbr,
+                vbr_residual_beam_width: if vbr { 2 } else { 0 },
+                ..Default::default()
+            };
+            let encoded = sea_encode(&input, TEST_SAMPLE_RATE, 2, settings).unwrap();
+
+            let decoded = sea_decode_i32(&encoded).unwrap();
+            assert_eq!(decoded.sample_depth, sample_depth);
+            assert_eq!(decoded.samples.len(), input.len());
+            let snr = snr(&input, &decoded.samples);
+            assert!(
+                snr > 12.0,
+                "depth={sample_depth} bits={residual_bits} snr={snr}"
+            );
+
+            // 16-bit output drops the additional resolution
+            let decoded_i16 = sea_decode(&encoded).unwrap();
+            assert_eq!(decoded_i16.sample_depth, sample_depth);
+            assert!(decoded_i16
+                .samples
+                .iter()
+                .zip(&decoded.samples)
+                .all(|(&a, &b)| a as i32 == b >> (sample_depth - 16)));
+        }
+    }
+}
+
+#[test]
+fn high_resolution_keeps_detail_below_16_bits() {
+    // quieter than the least significant bit of a 16-bit sample
+    let input: Vec<i32> = (0..20_000)
+        .map(|i| (100.0 * (i as f32 * 0.05).sin()) as i32)
+        .collect();
+
+    let encoded = sea_encode(
+        &input,
+        TEST_SAMPLE_RATE,
+        1,
+        EncoderSettings {
+            sample_depth: 24,
+            residual_bits: 4.0,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let decoded = sea_decode_i32(&encoded).unwrap().samples;
+    assert!(snr(&input, &decoded) > 20.0);
+}
+
+#[test]
+fn sixteen_bit_input_is_scaled_to_the_sample_depth() {
+    let input = gen_test_signal(1, 10_000);
+    let settings = EncoderSettings {
+        sample_depth: 24,
+        residual_bits: 5.0,
+        ..Default::default()
+    };
+
+    let scaled: Vec<i32> = input.iter().map(|&s| (s as i32) << 8).collect();
+    assert_eq!(
+        sea_encode(&input, TEST_SAMPLE_RATE, 1, settings.clone()).unwrap(),
+        sea_encode(&scaled, TEST_SAMPLE_RATE, 1, settings).unwrap()
+    );
+}
+
+#[test]
+fn sample_depth_is_stored_in_the_header() {
+    let input = gen_test_signal(1, 5000);
+
+    let encoded = sea_encode(&input, TEST_SAMPLE_RATE, 1, EncoderSettings::default()).unwrap();
+    let header = SeaDecoder::from_slice(&encoded).unwrap().get_header();
+    assert_eq!((header.version, header.sample_depth), (1, 16));
+
+    let encoded = sea_encode(
+        &input,
+        TEST_SAMPLE_RATE,
+        1,
+        EncoderSettings {
+            sample_depth: 20,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let header = SeaDecoder::from_slice(&encoded).unwrap().get_header();
+    assert_eq!((header.version, header.sample_depth), (2, 20));
+
+    // the last depth code is reserved
+    let mut reserved = encoded.clone();
+    reserved[18] |= 0x0c;
+    assert!(matches!(
+        SeaDecoder::from_slice(&reserved),
+        Err(SeaError::UnsupportedFlags { flags: 0x0c })
+    ));
+}
+
+#[test]
+fn unsupported_sample_depths_are_rejected() {
+    for sample_depth in [0, 8, 18, 32] {
+        let result = SeaEncoder::from_slice(
+            1,
+            TEST_SAMPLE_RATE,
+            None,
+            EncoderSettings {
+                sample_depth,
+                ..Default::default()
+            },
+            &[0i32; 100],
+        );
+        assert!(matches!(
+            result,
+            Err(SeaError::InvalidSettings {
+                field: "sample_depth",
+                ..
+            })
+        ));
+    }
+}
+
+#[test]
+fn high_resolution_sync_stream() {
+    let input = gen_high_res_signal(2, 10_000, 24);
+    let encoded = sea_encode(
+        &input,
+        TEST_SAMPLE_RATE,
+        2,
+        EncoderSettings {
+            frames_per_chunk: 1000,
+            sample_depth: 24,
+            sync: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let reference = sea_decode_i32(&encoded).unwrap().samples;
+
+    // join the stream in the middle of the file
+    let mut decoder = SeaDecoder::from_sync_slice(&encoded[encoded.len() / 2..]).unwrap();
+    assert_eq!(decoder.get_header().sample_depth, 24);
+    let mut output = vec![0i32; decoder.max_chunk_samples()];
+    let samples = decoder.decode_chunk_into(&mut output).unwrap();
+    assert!(reference
+        .windows(samples)
+        .any(|window| window == &output[..samples]));
+}
+
+#[test]
+fn embedded_decoder_high_resolution() {
+    let input = gen_high_res_signal(2, 5000, 24);
+    let encoded = sea_encode(
+        &input,
+        TEST_SAMPLE_RATE,
+        2,
+        EncoderSettings {
+            frames_per_chunk: 1000,
+            sample_depth: 24,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let reference = sea_decode_i32(&encoded).unwrap().samples;
+
+    let mut decoder = SeaEmbeddedDecoder::<2>::new(&encoded).unwrap();
+    assert_eq!(decoder.sample_depth(), 24);
+    let mut output = [0i32; 2000];
+    assert!(matches!(
+        decoder.decode_chunk(&DequantTables::<16>::new().unwrap(), &mut output),
+        Err(SeaError::TablesMismatch {
+            scale_factor_bits: 4,
+            sample_depth: 24
+        })
+    ));
+
+    // the chunk is still next after the mismatch
+    let tables = DequantTables::<16>::with_sample_depth(24).unwrap();
+    let mut decoded = Vec::new();
+    loop {
+        let samples = decoder.decode_chunk(&tables, &mut output).unwrap();
+        if samples == 0 {
+            break;
+        }
+        decoded.extend_from_slice(&output[..samples]);
+    }
+    assert_eq!(decoded, reference);
+
+    assert!(DequantTables::<16>::with_sample_depth(12).is_err());
+}
@@ -0,0 +1,66 @@
+use std::io::Cursor;
+
+use helpers::{gen_test_signal, TEST_SAMPLE_RATE};
+use sea_codec::{decoder::SeaDecoder, encoder::EncoderSettings, sea_decode, sea_encode};
+
+extern crate sea_codec;
+
+mod helpers;
+
+fn decode_rest(decoder: &mut SeaDecoder) -> Vec<i16> {
+    let mut decoded = Vec::new();
+    while decoder.decode_frame(&mut decoded).unwrap() {}
+    decoded
+}
+
+#[test]
+fn seek_to_frame_matches_full_decode() {
+    for vbr in [false, true] {
+        let channels = 2;
+        let input = gen_test_signal(channels, TEST_SAMPLE_RATE as usize / 4);
+        let settings = EncoderSettings {
+            frames_per_chunk: 1000,
+            vbr,
+            ..Default::default()
+        };
+        let encoded = sea_encode(&input, TEST_SAMPLE_RATE, channels, settings).unwrap();
+        let reference = sea_decode(&encoded).unwrap().samples;
+        let total_frames = input.len() / channels as usize;
+
+        for frame in [0, 1, 999, 1000, 1001, 4321, total_frames - 1, total_frames] {
+            let expected = &reference[frame * channels as usize..];
+
+            let mut decoder = SeaDecoder::from_slice(&encoded).unwrap();
+            decoder.seek_to_frame(frame as u64).unwrap();
+            assert_eq!(decoder.position(), frame as u64);
+            assert_eq!(decode_rest(&mut decoder), expected);
+
+            let mut decoder = SeaDecoder::from_seekable_reader(Cursor::new(&encoded)).unwrap();
+            decoder.seek_to_frame(frame as u64).unwrap();
+            assert_eq!(decode_rest(&mut decoder), expected);
+        }
+    }
+}
+
+#[test]
+fn seek_backwards_after_decoding() {
+    let input = gen_test_signal(1, TEST_SAMPLE_RATE as usize / 4);
+    let encoded = sea_encode(&input, TEST_SAMPLE_RATE, 1, EncoderSettings::default()).unwrap();
+    let reference = sea_decode(&encoded).unwrap().samples;
+
+    let mut decoder = SeaDecoder::from_slice(&encoded).unwrap();
+    decode_rest(&mut decoder);
+    decoder.seek_to_frame(123).unwrap();
+    assert_eq!(decode_rest(&mut decoder), &reference[123..]);
+
+    assert!(decoder.seek_to_frame(input.len() as u64 + 1).is_err());
+}
+
+#[test]
+fn seek_requires_seekable_reader() {
+    let input = gen_test_signal(1, 1000);
+    let encoded = sea_encode(&input, TEST_SAMPLE_RATE, 1, EncoderSettings::default()).unwrap();
+
+    let mut decoder = SeaDecoder::from_reader(encoded.as_slice()).unwrap();
+    assert!(decoder.seek_to_frame(10).is_err());
+}
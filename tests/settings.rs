@@ -0,0 +1,159 @@
+use helpers::{gen_test_signal, TEST_SAMPLE_RATE};
+use sea_codec::{
+    encoder::{EncoderSettings, SeaEncoder, SeaStreamEncoder},
+    sea_encode, SeaError,
+};
+
+extern crate sea_codec;
+
+mod helpers;
+
+fn invalid_field(settings: EncoderSettings) -> &'static str {
+    match settings.validate() {
+        Err(SeaError::InvalidSettings { field, .. }) => field,
+        other => panic!("expected invalid settings, got {other:?}"),
+    }
+}
+
+#[test]
+fn default_settings_are_valid() {
+    assert!(EncoderSettings::default().validate().is_ok());
+    assert!(EncoderSettings {
+        vbr: true,
+        residual_bits: 2.5,
+        ..Default::default()
+    }
+    .validate()
+    .is_ok());
+}
+
+#[test]
+fn invalid_settings_name_the_field() {
+    let cases = [
+        (
+            EncoderSettings {
+                frames_per_chunk: 100,
+                ..Default::default()
+            },
+            "frames_per_chunk",
+        ),
+        (
+            EncoderSettings {
+                frames_per_chunk: 40000,
+                ..Default::default()
+            },
+            "frames_per_chunk",
+        ),
+        (
+            EncoderSettings {
+                scale_factor_bits: 6,
+                ..Default::default()
+            },
+            "scale_factor_bits",
+        ),
+        (
+            EncoderSettings {
+                scale_factor_frames: 0,
+                ..Default::default()
+            },
+            "scale_factor_frames",
+        ),
+        (
+            EncoderSettings {
+                frames_per_chunk: 1000,
+                scale_factor_frames: 30,
+                ..Default::default()
+            },
+            "scale_factor_frames",
+        ),
+        (
+            EncoderSettings {
+                scale_factor_frames: 40,
+                vbr_residual_beam_width: 2,
+                ..Default::default()
+            },
+            "scale_factor_frames",
+        ),
+        (
+            EncoderSettings {
+                residual_bits: 2.5,
+                ..Default::default()
+            },
+            "residual_bits",
+        ),
+        (
+            EncoderSettings {
+                residual_bits: 9.0,
+                ..Default::default()
+            },
+            "residual_bits",
+        ),
+        (
+            EncoderSettings {
+                vbr: true,
+                residual_bits: 1.0,
+                ..Default::default()
+            },
+            "residual_bits",
+        ),
+        (
+            EncoderSettings {
+                residual_bits: f32::NAN,
+                ..Default::default()
+            },
+            "residual_bits",
+        ),
+        (
+            EncoderSettings {
+                sample_depth: 32,
+                ..Default::default()
+            },
+            "sample_depth",
+        ),
+    ];
+
+    for (settings, field) in cases {
+        assert_eq!(invalid_field(settings), field);
+    }
+}
+
+#[test]
+fn encoders_reject_invalid_settings() {
+    let input = gen_test_signal(2, 1000);
+    let settings = EncoderSettings {
+        scale_factor_bits: 2,
+        ..Default::default()
+    };
+
+    assert!(matches!(
+        sea_encode(&input, TEST_SAMPLE_RATE, 2, settings.clone()),
+        Err(SeaError::InvalidSettings {
+            field: "scale_factor_bits",
+            ..
+        })
+    ));
+    assert!(matches!(
+        SeaEncoder::from_slice(2, TEST_SAMPLE_RATE, None, settings.clone(), &input),
+        Err(SeaError::InvalidSettings { .. })
+    ));
+    assert!(matches!(
+        SeaStreamEncoder::new(2, TEST_SAMPLE_RATE, settings),
+        Err(SeaError::InvalidSettings { .. })
+    ));
+}
+
+#[test]
+fn sea_encode_rejects_invalid_channel_counts() {
+    let input = gen_test_signal(1, 1000);
+    for channels in [0, 256] {
+        assert!(matches!(
+            sea_encode(
+                &input,
+                TEST_SAMPLE_RATE,
+                channels,
+                EncoderSettings::default()
+            ),
+            Err(SeaError::InvalidParameters)
+        ));
+    }
+}
@@ -7,7 +7,8 @@ use std::{
 use helpers::{encode_decode, gen_test_signal, TEST_SAMPLE_RATE};
 use sea_codec::{
     decoder::SeaDecoder,
-    encoder::{EncoderSettings, SeaEncoder},
+    encoder::{EncoderSettings, SeaEncoder, SeaStreamEncoder},
+    sea_decode, sea_encode,
 };
 
 extern crate sea_codec;
@@ -90,3 +91,152 @@ fn streaming() {
         sea_decoded[..]
     );
 }
+
+#[test]
+fn stream_encoder_matches_slice_encoder() {
+    let channels = 2;
+    let settings = EncoderSettings {
+        frames_per_chunk: 1000,
+        ..Default::default()
+    };
+    // a whole number of chunks, the last partial chunk is not decodable in streaming mode
+    let mut input_samples = gen_test_signal(channels, 10_000);
+    input_samples.truncate(10_000 * channels as usize);
+
+    let mut reference = Vec::new();
+    let mut slice_encoder = SeaEncoder::from_slice(
+        channels as u8,
+        TEST_SAMPLE_RATE,
+        None,
+        settings.clone(),
+        &input_samples,
+    )
+    .unwrap();
+    while slice_encoder.encode_frame(&mut reference).unwrap() {}
+
+    let mut encoded = Vec::new();
+    let mut stream_encoder =
+        SeaStreamEncoder::new(channels as u8, TEST_SAMPLE_RATE, settings).unwrap();
+
+    // odd sized pushes, which also split frames between calls
+    for samples in input_samples.chunks(777) {
+        stream_encoder.push_samples(samples, &mut encoded).unwrap();
+    }
+    assert_eq!(stream_encoder.written_frames(), 10_000);
+    stream_encoder.finalize(&mut encoded).unwrap();
+
+    assert_eq!(encoded, reference);
+    assert!(stream_encoder.push_samples(&[0, 0], &mut encoded).is_err());
+
+    let mut decoded = Vec::new();
+    let mut decoder = SeaDecoder::from_slice(&encoded).unwrap();
+    while decoder.decode_frame(&mut decoded).unwrap() {}
+    assert_eq!(
+        decoded,
+        encode_decode(
+            &input_samples,
+            TEST_SAMPLE_RATE,
+            channels,
+            EncoderSettings {
+                frames_per_chunk: 1000,
+                ..Default::default()
+            }
+        )
+        .decoded
+    );
+}
+
+#[test]
+fn stream_encoder_emits_chunks_as_they_fill() {
+    let input_samples = gen_test_signal(1, 2500);
+    let mut stream_encoder =
+        SeaStreamEncoder::new(1, TEST_SAMPLE_RATE, EncoderSettings::default()).unwrap();
+
+    let mut encoded = Vec::new();
+    stream_encoder
+        .push_samples(&input_samples[..2000], &mut encoded)
+        .unwrap();
+    assert!(encoded.is_empty());
+
+    let mut encoded = Vec::new();
+    let mut long_input = input_samples.repeat(3);
+    long_input.truncate(EncoderSettings::default().frames_per_chunk as usize - 2000);
+    stream_encoder
+        .push_samples(&long_input, &mut encoded)
+        .unwrap();
+    assert!(!encoded.is_empty());
+    assert_eq!(
+        stream_encoder.written_frames(),
+        EncoderSettings::default().frames_per_chunk as u64
+    );
+
+    stream_encoder
+        .push_samples(&input_samples, &mut encoded)
+        .unwrap();
+    let before_finalize = encoded.len();
+    stream_encoder.finalize(&mut encoded).unwrap();
+    assert!(encoded.len() > before_finalize);
+    assert!(stream_encoder.finalize(&mut encoded).is_err());
+}
+
+#[test]
+fn stream_encoder_patches_header_on_seekable_finalize() {
+    let channels = 2;
+    let input_samples = gen_test_signal(channels, 10_000);
+    let settings = EncoderSettings {
+        frames_per_chunk: 1000,
+        ..Default::default()
+    };
+
+    let mut output = io::Cursor::new(Vec::new());
+    let mut stream_encoder =
+        SeaStreamEncoder::new(channels as u8, TEST_SAMPLE_RATE, settings.clone()).unwrap();
+    for samples in input_samples.chunks(1234) {
+        stream_encoder.push_samples(samples, &mut output).unwrap();
+    }
+    stream_encoder.finalize_seekable(&mut output).unwrap();
+    let encoded = output.into_inner();
+
+    // identical to encoding with a known frame count
+    let reference = encode_decode(&input_samples, TEST_SAMPLE_RATE, channels, settings);
+    assert_eq!(encoded, reference.encoded);
+
+    let mut decoded = Vec::new();
+    let mut decoder = SeaDecoder::from_slice(&encoded).unwrap();
+    assert_eq!(
+        decoder.get_header().total_frames as usize,
+        input_samples.len() / channels as usize
+    );
+    while decoder.decode_frame(&mut decoded).unwrap() {}
+    assert_eq!(decoded, reference.decoded);
+}
+
+#[test]
+fn streams_without_frames_decode_to_nothing() {
+    let channels = 2;
+    let settings = EncoderSettings::default();
+
+    let mut output = io::Cursor::new(Vec::new());
+    let mut stream_encoder =
+        SeaStreamEncoder::new(channels as u8, TEST_SAMPLE_RATE, settings.clone()).unwrap();
+    stream_encoder.finalize_seekable(&mut output).unwrap();
+    let encoded = output.into_inner();
+
+    // identical to encoding an empty slice
+    let reference = sea_encode::<i16>(&[], TEST_SAMPLE_RATE, channels, settings.clone()).unwrap();
+    assert_eq!(encoded, reference);
+
+    let mut unseekable = Vec::new();
+    let mut stream_encoder =
+        SeaStreamEncoder::new(channels as u8, TEST_SAMPLE_RATE, settings).unwrap();
+    stream_encoder.finalize(&mut unseekable).unwrap();
+
+    for encoded in [encoded, unseekable] {
+        let decoder = SeaDecoder::from_slice(&encoded).unwrap();
+        assert_eq!(decoder.get_header().total_frames, 0);
+        assert_ne!(decoder.get_header().chunk_size, 0);
+        let decoded = sea_decode(&encoded).unwrap();
+        assert!(decoded.samples.is_empty());
+        assert_eq!(decoded.channels, channels);
+    }
+}
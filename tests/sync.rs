@@ -0,0 +1,111 @@
+use helpers::{gen_test_signal, TEST_SAMPLE_RATE};
+use sea_codec::{decoder::SeaDecoder, encoder::EncoderSettings, sea_decode, sea_encode};
+
+extern crate sea_codec;
+
+mod helpers;
+
+const CHANNELS: u32 = 2;
+const FRAMES_PER_CHUNK: usize = 1000;
+const CHUNK_SAMPLES: usize = FRAMES_PER_CHUNK * CHANNELS as usize;
+
+fn encode(sync: bool, checksum: bool) -> Vec<u8> {
+    let input = gen_test_signal(CHANNELS, 5000);
+    sea_encode(
+        &input,
+        TEST_SAMPLE_RATE,
+        CHANNELS,
+        EncoderSettings {
+            frames_per_chunk: FRAMES_PER_CHUNK as u16,
+            vbr: true,
+            sync,
+            checksum,
+            ..Default::default()
+        },
+    )
+    .unwrap()
+}
+
+fn decode_all(mut decoder: SeaDecoder) -> Vec<i16> {
+    let mut output = Vec::new();
+    while decoder.decode_frame(&mut output).unwrap() {}
+    output
+}
+
+// returns the offset of the first chunk and the distance between chunks
+fn chunk_layout(encoded: &[u8]) -> (usize, usize) {
+    let header = SeaDecoder::from_slice(encoded).unwrap().get_header();
+    (header.serialize().len(), header.chunk_stride())
+}
+
+#[test]
+fn sync_framing_decodes_like_plain_files() {
+    let plain = encode(false, false);
+    let synced = encode(true, false);
+
+    assert_eq!(synced[4], 2);
+    assert!(synced.len() > plain.len());
+    assert_eq!(
+        sea_decode(&synced).unwrap().samples,
+        sea_decode(&plain).unwrap().samples
+    );
+}
+
+#[test]
+fn joins_stream_at_any_offset() {
+    let encoded = encode(true, false);
+    let reference = sea_decode(&encoded).unwrap().samples;
+    let (data_start, stride) = chunk_layout(&encoded);
+    // skip the file header and half of the first chunk
+    let offset = data_start + stride / 2;
+
+    let decoded = decode_all(SeaDecoder::from_sync_slice(&encoded[offset..]).unwrap());
+    assert_eq!(decoded, reference[CHUNK_SAMPLES..]);
+
+    let decoded = decode_all(SeaDecoder::from_sync_reader(&encoded[offset..]).unwrap());
+    assert_eq!(decoded, reference[CHUNK_SAMPLES..]);
+
+    // joining exactly at a chunk boundary keeps that chunk
+    let decoded = decode_all(SeaDecoder::from_sync_slice(&encoded[data_start..]).unwrap());
+    assert_eq!(decoded, reference);
+}
+
+#[test]
+fn resynchronises_after_damaged_data() {
+    let encoded = encode(true, true);
+    let reference = sea_decode(&encoded).unwrap().samples;
+    let (data_start, stride) = chunk_layout(&encoded);
+
+    // lose the second half of the second chunk and insert some garbage instead
+    let cut_start = data_start + stride + stride / 2;
+    let cut_end = data_start + stride * 2;
+    let mut damaged = encoded[..cut_start].to_vec();
+    damaged.extend_from_slice(b"SEASgarbage");
+    damaged.extend_from_slice(&encoded[cut_end..]);
+
+    let mut decoder = SeaDecoder::from_sync_reader(&damaged[data_start..]).unwrap();
+    let mut output = Vec::new();
+    assert!(decoder.decode_frame(&mut output).unwrap());
+    // the damaged chunk swallows the following sync header and fails the checksum
+    assert!(decoder.decode_frame(&mut output).is_err());
+    while decoder.decode_frame(&mut output).unwrap() {}
+
+    assert_eq!(output[..CHUNK_SAMPLES], reference[..CHUNK_SAMPLES]);
+    assert_eq!(output[CHUNK_SAMPLES..], reference[CHUNK_SAMPLES * 3..]);
+}
+
+#[test]
+fn seeks_after_joining_stream() {
+    let encoded = encode(true, false);
+    let reference = sea_decode(&encoded).unwrap().samples;
+    let (data_start, stride) = chunk_layout(&encoded);
+    let joined = &encoded[data_start + stride / 2..];
+
+    // frames count from the first complete chunk after the join
+    for (frame, expected_start) in [(0, 1000), (2500, 3500)] {
+        let mut decoder = SeaDecoder::from_sync_slice(joined).unwrap();
+        decoder.seek_to_frame(frame).unwrap();
+        let decoded = decode_all(decoder);
+        assert_eq!(decoded, reference[expected_start * CHANNELS as usize..]);
+    }
+}
@@ -19,10 +19,11 @@ fn vbr_effort_levels_keep_the_legacy_chunk_format() {
                 vbr_residual_beam_width,
                 ..Default::default()
             },
-        );
+        )
+        .unwrap();
 
         assert_eq!(encoded[22], 0x02);
-        assert_eq!(sea_decode(&encoded).samples.len(), input.len());
+        assert_eq!(sea_decode(&encoded).unwrap().samples.len(), input.len());
     }
 }
 
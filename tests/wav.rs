@@ -1,11 +1,15 @@
 use std::{error::Error, fs::File, path::Path};
 
 use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+use sea_codec::convert::{FloatConversion, SampleConverter};
 
 pub struct Wave {
-    pub samples: Vec<i16>,
+    /// Samples at `sample_depth` bits
+    pub samples: Vec<i32>,
     pub channels: u32,
     pub sample_rate: u32,
+    /// 16 for 8 and 16-bit input, 24 for higher resolution input
+    pub sample_depth: u8,
 }
 
 pub fn read_wav(path: &Path) -> Result<Wave, Box<dyn Error>> {
@@ -16,58 +20,63 @@ pub fn read_wav(path: &Path) -> Result<Wave, Box<dyn Error>> {
         return Err("More than 2 channels are not supported".into());
     }
 
-    let samples_iter: Box<dyn Iterator<Item = i16>> =
+    let (samples_iter, sample_depth): (Box<dyn Iterator<Item = i32>>, u8) =
         match (spec.sample_format, spec.bits_per_sample) {
-            (SampleFormat::Int, 8) => Box::new(reader.samples::<i8>().map(|s| {
-                let s = s.unwrap();
-                (s as i16) << 8
-            })),
-            (SampleFormat::Int, 16) => Box::new(reader.samples::<i16>().map(|s| s.unwrap())),
-            (SampleFormat::Int, 24) => Box::new(reader.samples::<i32>().map(|s| {
-                let s = s.unwrap();
-                ((s as f32 / (1 << 23) as f32) * i16::MAX as f32).round() as i16
-            })),
-            (SampleFormat::Int, 32) => Box::new(reader.samples::<i32>().map(|s| {
-                let s = s.unwrap();
-                ((s as f32 / i32::MAX as f32) * i16::MAX as f32).round() as i16
-            })),
-            (SampleFormat::Float, 32) => Box::new(reader.samples::<f32>().map(|s| {
-                let s = s.unwrap();
-                (s * i16::MAX as f32).round() as i16
-            })),
+            (SampleFormat::Int, 8) => (
+                Box::new(reader.samples::<i8>().map(|s| (s.unwrap() as i32) << 8)),
+                16,
+            ),
+            (SampleFormat::Int, 16) => (
+                Box::new(reader.samples::<i16>().map(|s| s.unwrap() as i32)),
+                16,
+            ),
+            (SampleFormat::Int, 24) => (Box::new(reader.samples::<i32>().map(|s| s.unwrap())), 24),
+            (SampleFormat::Int, 32) => (
+                Box::new(reader.samples::<i32>().map(|s| s.unwrap() >> 8)),
+                24,
+            ),
+            (SampleFormat::Float, 32) => {
+                let input = reader.samples::<f32>().collect::<Result<Vec<_>, _>>()?;
+                let mut samples = Vec::with_capacity(input.len());
+                SampleConverter::new(spec.channels as u8, 24, FloatConversion::Round)?
+                    .convert(&input, &mut samples);
+                (Box::new(samples.into_iter()), 24)
+            }
             (format, bits) => {
                 return Err(format!("Unsupported format: {:?} with {} bits", format, bits).into())
             }
         };
 
-    let samples: Vec<i16> = samples_iter.collect();
+    let samples: Vec<i32> = samples_iter.collect();
 
     Ok(Wave {
         samples,
         channels: spec.channels as u32,
         sample_rate: spec.sample_rate,
+        sample_depth,
     })
 }
 
+/// Writes 16-bit samples as a 16-bit file, higher resolution samples as a 24-bit file
 pub fn write_wav(
-    wave: &[i16],
+    wave: &[i32],
     channels: u16,
     sample_rate: u32,
+    sample_depth: u8,
     output_path: &str,
 ) -> Result<(), Box<dyn Error>> {
+    let bits_per_sample = if sample_depth > 16 { 24 } else { 16 };
     let spec = WavSpec {
         channels,
         sample_rate,
-        bits_per_sample: 16,
+        bits_per_sample,
         sample_format: SampleFormat::Int,
     };
 
     let mut writer: WavWriter<std::io::BufWriter<File>> =
         WavWriter::create(output_path, spec).unwrap();
-    // let start = sample.get_start() as usize;
-    // let end = sample.get_end() as usize;
     for item in wave {
-        writer.write_sample(*item)?;
+        writer.write_sample(*item << (bits_per_sample - sample_depth as u16))?;
     }
     writer.finalize()?;
 
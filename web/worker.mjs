@@ -61,7 +61,7 @@ let wasm;
           wasmOutputBufferSize
         );
 
-        if (outputLength === 0) throw new Error("Encoding failed: Output buffer too small.");
+        if (outputLength === 0) throw new Error("Encoding failed: Invalid settings or output buffer too small.");
 
         const output = new Uint8Array(
           wasmExports.memory.buffer,
@@ -108,7 +108,7 @@ let wasm;
           wasmChannelsBuffer
         );
 
-        if (outputLength === 0) throw new Error("Decoding failed: Got zero output length.");
+        if (outputLength === 0) throw new Error("Decoding failed: Invalid file or output buffer too small.");
 
         const output = new Int16Array(
           wasmExports.memory.buffer,